@@ -7,7 +7,7 @@ async fn main() {
     let res = ipinfo.get_map(&["8.8.8.8", "4.2.2.4"]).await;
     match res {
         Ok(r) => {
-            println!("Map URL: {}", r);
+            println!("Map URL: {}", r.report_url);
         }
         Err(e) => println!("error occurred: {}", &e.to_string()),
     }
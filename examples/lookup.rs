@@ -1,9 +1,9 @@
-use ipinfo::{IpInfo, IpInfoConfig};
+use ipinfo::{IpInfo, IpInfoConfig, Token};
 use std::env;
 
 #[tokio::main]
 async fn main() {
-    let token = env::args().nth(1);
+    let token = env::args().nth(1).map(Token::from);
 
     let config = IpInfoConfig {
         token,
@@ -1,9 +1,9 @@
-use ipinfo::{IpInfoLite, IpInfoLiteConfig};
+use ipinfo::{IpInfoLite, IpInfoLiteConfig, Token};
 use std::env;
 
 #[tokio::main]
 async fn main() {
-    let token = env::args().nth(1);
+    let token = env::args().nth(1).map(Token::from);
 
     let config = IpInfoLiteConfig {
         token,
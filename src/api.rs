@@ -14,11 +14,13 @@
 
 //! IPinfo API data structures.
 
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::Coordinates;
+
 /// IP address lookup details.
 #[derive(Debug, Default, Deserialize, Serialize, Clone)]
 pub struct IpDetails {
@@ -458,6 +460,61 @@ pub struct IpDetailsPlus {
     pub extra: HashMap<String, Value>,
 }
 
+/// Details common to the lookups of all tiers.
+///
+/// Build it with `IpDetailsCommon::from(&details)` from the details of any
+/// tier. Fields a tier does not provide are `None`.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct IpDetailsCommon {
+    /// The IP address.
+    pub ip: String,
+
+    /// The reverse DNS lookup hostname of the IP address.
+    pub hostname: Option<String>,
+
+    /// The city for the IP address.
+    pub city: Option<String>,
+
+    /// The region for the IP address.
+    pub region: Option<String>,
+
+    /// The ISO 3166 country code for the IP address.
+    pub country_code: Option<String>,
+
+    /// The country name for the IP address.
+    pub country_name: Option<String>,
+
+    /// Code and name of the continent.
+    pub continent: Option<Continent>,
+
+    /// The geographical location for the IP address.
+    pub coordinates: Option<Coordinates>,
+
+    /// The postal code for the IP address.
+    pub postal_code: Option<String>,
+
+    /// The timezone for the IP address.
+    pub timezone: Option<String>,
+
+    /// The AS number the IP address is part of.
+    pub asn: Option<String>,
+
+    /// The AS name.
+    pub as_name: Option<String>,
+
+    /// The AS domain.
+    pub as_domain: Option<String>,
+
+    /// Whether the IP address belongs to a hosting provider.
+    pub is_hosting: Option<bool>,
+
+    /// Whether the IP address is a VPN, proxy, relay or Tor exit.
+    pub is_anonymous: Option<bool>,
+
+    /// If the IP Address is Bogon
+    pub bogon: Option<bool>,
+}
+
 /// Residential proxy detection details.
 #[derive(Debug, Default, Deserialize, Serialize, Clone)]
 pub struct ResproxyDetails {
@@ -474,3 +531,188 @@ pub struct ResproxyDetails {
     /// The residential proxy service name.
     pub service: Option<String>,
 }
+
+/// Map report of a list of IP addresses.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MapReport {
+    /// The status of the report generation.
+    pub status: Option<String>,
+
+    /// The URL of the generated map report.
+    pub report_url: String,
+
+    /// Any other fields of the response.
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// Summary of a list of IP addresses.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct IpSummary {
+    /// The total number of IP addresses.
+    pub total: u64,
+
+    /// The number of unique IP addresses.
+    pub unique: u64,
+
+    /// The number of IP addresses per country.
+    pub countries: HashMap<String, u64>,
+
+    /// The number of IP addresses per city.
+    pub cities: HashMap<String, u64>,
+
+    /// The number of IP addresses per region.
+    pub regions: HashMap<String, u64>,
+
+    /// The number of IP addresses per AS.
+    pub asns: HashMap<String, u64>,
+
+    /// The number of IP addresses per company.
+    pub companies: HashMap<String, u64>,
+
+    /// The number of IP addresses per IP type.
+    pub ip_types: HashMap<String, u64>,
+
+    /// The number of IP addresses per route.
+    pub routes: HashMap<String, u64>,
+
+    /// The number of IP addresses per mobile carrier.
+    pub carriers: HashMap<String, u64>,
+
+    /// The number of mobile IP addresses.
+    pub mobile: u64,
+
+    /// The number of IP addresses per hosted domain.
+    pub domains: HashMap<String, u64>,
+
+    /// The number of IP addresses per privacy category.
+    pub privacy: IpSummaryPrivacy,
+
+    /// The number of IP addresses per privacy service.
+    pub privacy_services: HashMap<String, u64>,
+
+    /// The number of anycast IP addresses.
+    pub anycast: u64,
+
+    /// The number of bogon IP addresses.
+    pub bogon: u64,
+}
+
+/// Privacy counts of an IP address summary.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct IpSummaryPrivacy {
+    /// The number of IP addresses belonging to a VPN.
+    pub vpn: u64,
+
+    /// The number of IP addresses belonging to a proxy.
+    pub proxy: u64,
+
+    /// The number of IP addresses from a hosting provider.
+    pub hosting: u64,
+
+    /// The number of IP addresses which are a relay.
+    pub relay: u64,
+
+    /// The number of IP addresses using Tor.
+    pub tor: u64,
+}
+
+/// Account details of the access token.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct AccountInfo {
+    /// The plan the token belongs to.
+    pub plan: Option<String>,
+
+    /// Request usage and quota.
+    pub requests: AccountRequests,
+
+    /// The features enabled for the token and their limits.
+    pub features: HashMap<String, Value>,
+}
+
+/// Request usage and quota of an account.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct AccountRequests {
+    /// The number of requests made today.
+    pub day: u64,
+
+    /// The number of requests made this month.
+    pub month: u64,
+
+    /// The monthly request limit.
+    pub limit: u64,
+
+    /// The number of requests remaining this month.
+    pub remaining: u64,
+}
+
+/// A single field of the IP address details, which can be looked up on its
+/// own.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum IpField {
+    /// The city for the IP address.
+    City,
+
+    /// The region for the IP address.
+    Region,
+
+    /// The country code for the IP address.
+    Country,
+
+    /// The geographical location ("latitude,longitude") for the IP address.
+    Loc,
+
+    /// The organization for the IP address.
+    Org,
+
+    /// The postal code for the IP address.
+    Postal,
+
+    /// The timezone for the IP address.
+    Timezone,
+
+    /// The reverse DNS lookup hostname of the IP address.
+    Hostname,
+}
+
+impl IpField {
+    /// Get the API path segment of the field.
+    pub fn as_str(&self) -> &str {
+        match self {
+            IpField::City => "city",
+            IpField::Region => "region",
+            IpField::Country => "country",
+            IpField::Loc => "loc",
+            IpField::Org => "org",
+            IpField::Postal => "postal",
+            IpField::Timezone => "timezone",
+            IpField::Hostname => "hostname",
+        }
+    }
+
+    // The value of the field in the full details, if it has one
+    pub(crate) fn value(&self, details: &IpDetails) -> Option<String> {
+        let value = match self {
+            IpField::City => Some(&details.city),
+            IpField::Region => Some(&details.region),
+            IpField::Country => Some(&details.country),
+            IpField::Loc => Some(&details.loc),
+            IpField::Org => details.org.as_ref(),
+            IpField::Postal => details.postal.as_ref(),
+            IpField::Timezone => details.timezone.as_ref(),
+            IpField::Hostname => details.hostname.as_ref(),
+        };
+        value.filter(|value| !value.is_empty()).cloned()
+    }
+}
+
+impl fmt::Display for IpField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
@@ -0,0 +1,371 @@
+//   Copyright 2019-2025 IPinfo library developers
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Synchronous versions of the IPinfo clients.
+//!
+//! The blocking clients take the same configuration as their asynchronous
+//! counterparts and share their caching and enrichment behavior. Each one
+//! drives requests on its own internal runtime, so they must not be used
+//! from within an asynchronous runtime.
+//!
+//! Requires the `blocking` feature.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use ipinfo::{blocking::IpInfo, IpInfoConfig};
+//!
+//! let config = IpInfoConfig {
+//!     token: Some("my token".into()),
+//!     ..Default::default()
+//! };
+//! let mut ipinfo = IpInfo::new(config).expect("should construct");
+//! let res = ipinfo.lookup("8.8.8.8");
+//! ```
+
+use std::collections::HashMap;
+
+use ipnetwork::IpNetwork;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::{
+    AccountInfo, BatchReqOpts, CacheSnapshot, IpDetails, IpDetailsCore,
+    IpDetailsLite, IpDetailsPlus, IpError, IpField, IpInfoConfig,
+    IpInfoCoreConfig, IpInfoLiteConfig, IpInfoPlusConfig, IpSummary,
+    MapReport, ResproxyDetails,
+};
+
+// Build the runtime used to drive the asynchronous client
+fn runtime() -> Result<Runtime, IpError> {
+    Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| {
+            err!(ConfigError, &format!("failed to build runtime: {e}"))
+        })
+}
+
+/// Synchronous IPinfo requests context structure.
+pub struct IpInfo {
+    inner: crate::IpInfo,
+    runtime: Runtime,
+}
+
+impl IpInfo {
+    /// Construct a new blocking IpInfo structure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipinfo::blocking::IpInfo;
+    ///
+    /// let ipinfo = IpInfo::new(Default::default()).expect("should construct");
+    /// ```
+    pub fn new(config: IpInfoConfig) -> Result<Self, IpError> {
+        Ok(Self {
+            inner: crate::IpInfo::new(config)?,
+            runtime: runtime()?,
+        })
+    }
+
+    /// Lookup IPDetails for a list of one or more IP addresses.
+    pub fn lookup_batch(
+        &mut self,
+        ips: &[&str],
+        batch_config: BatchReqOpts,
+    ) -> Result<HashMap<String, IpDetails>, IpError> {
+        self.runtime
+            .block_on(self.inner.lookup_batch(ips, batch_config))
+    }
+
+    /// looks up IPDetails for a single IP Address
+    pub fn lookup(&mut self, ip: &str) -> Result<IpDetails, IpError> {
+        self.runtime.block_on(self.inner.lookup(ip))
+    }
+
+    /// looks up IPDetails of your own v4 IP
+    pub fn lookup_self_v4(&mut self) -> Result<IpDetails, IpError> {
+        self.runtime.block_on(self.inner.lookup_self_v4())
+    }
+
+    /// looks up IPDetails of your own v6 IP
+    pub fn lookup_self_v6(&mut self) -> Result<IpDetails, IpError> {
+        self.runtime.block_on(self.inner.lookup_self_v6())
+    }
+
+    /// Snapshot the cached lookup results
+    pub fn export_cache(&self) -> CacheSnapshot<IpDetails> {
+        self.inner.export_cache()
+    }
+
+    /// Preload the cache from a snapshot
+    pub fn import_cache(
+        &mut self,
+        snapshot: CacheSnapshot<IpDetails>,
+    ) -> usize {
+        self.inner.import_cache(snapshot)
+    }
+
+    /// looks up a single field of the IPDetails for an IP Address
+    pub fn lookup_field(
+        &mut self,
+        ip: &str,
+        field: IpField,
+    ) -> Result<Option<String>, IpError> {
+        self.runtime.block_on(self.inner.lookup_field(ip, field))
+    }
+
+    /// Get a mapping of a list of IPs on a world map
+    pub fn get_map<I>(&self, ips: I) -> Result<MapReport, IpError>
+    where
+        I: IntoIterator,
+        I::Item: ToString,
+    {
+        self.runtime.block_on(self.inner.get_map(ips))
+    }
+
+    /// Get summary statistics of a list of IPs
+    pub fn summarize_ips<I>(&self, ips: I) -> Result<IpSummary, IpError>
+    where
+        I: IntoIterator,
+        I::Item: ToString,
+    {
+        self.runtime.block_on(self.inner.summarize_ips(ips))
+    }
+
+    /// Looks up the IP ranges owned by a domain
+    pub fn lookup_ranges(
+        &self,
+        domain: &str,
+    ) -> Result<Vec<IpNetwork>, IpError> {
+        self.runtime.block_on(self.inner.lookup_ranges(domain))
+    }
+
+    /// Get the plan, request quota and features of the access token
+    pub fn account_info(&self) -> Result<AccountInfo, IpError> {
+        self.runtime.block_on(self.inner.account_info())
+    }
+
+    /// Looks up residential proxy details for a single IP address
+    pub fn lookup_resproxy(
+        &self,
+        ip: &str,
+    ) -> Result<ResproxyDetails, IpError> {
+        self.runtime.block_on(self.inner.lookup_resproxy(ip))
+    }
+}
+
+/// Synchronous IpInfoLite requests context structure.
+pub struct IpInfoLite {
+    inner: crate::IpInfoLite,
+    runtime: Runtime,
+}
+
+impl IpInfoLite {
+    /// Construct a new blocking IpInfoLite structure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipinfo::blocking::IpInfoLite;
+    ///
+    /// let ipinfo = IpInfoLite::new(Default::default()).expect("should construct");
+    /// ```
+    pub fn new(config: IpInfoLiteConfig) -> Result<Self, IpError> {
+        Ok(Self {
+            inner: crate::IpInfoLite::new(config)?,
+            runtime: runtime()?,
+        })
+    }
+
+    /// looks up IpDetailsLite for a single IP Address
+    pub fn lookup(&mut self, ip: &str) -> Result<IpDetailsLite, IpError> {
+        self.runtime.block_on(self.inner.lookup(ip))
+    }
+
+    /// looks up IPDetailsLite of your own v4 IP
+    pub fn lookup_self_v4(&mut self) -> Result<IpDetailsLite, IpError> {
+        self.runtime.block_on(self.inner.lookup_self_v4())
+    }
+
+    /// looks up IPDetailsLite of your own v6 IP
+    pub fn lookup_self_v6(&mut self) -> Result<IpDetailsLite, IpError> {
+        self.runtime.block_on(self.inner.lookup_self_v6())
+    }
+
+    /// Snapshot the cached lookup results
+    pub fn export_cache(&self) -> CacheSnapshot<IpDetailsLite> {
+        self.inner.export_cache()
+    }
+
+    /// Preload the cache from a snapshot
+    pub fn import_cache(
+        &mut self,
+        snapshot: CacheSnapshot<IpDetailsLite>,
+    ) -> usize {
+        self.inner.import_cache(snapshot)
+    }
+}
+
+/// Synchronous IpInfoCore requests context structure.
+pub struct IpInfoCore {
+    inner: crate::IpInfoCore,
+    runtime: Runtime,
+}
+
+impl IpInfoCore {
+    /// Construct a new blocking IpInfoCore structure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipinfo::blocking::IpInfoCore;
+    ///
+    /// let ipinfo = IpInfoCore::new(Default::default()).expect("should construct");
+    /// ```
+    pub fn new(config: IpInfoCoreConfig) -> Result<Self, IpError> {
+        Ok(Self {
+            inner: crate::IpInfoCore::new(config)?,
+            runtime: runtime()?,
+        })
+    }
+
+    /// looks up IpDetailsCore for a single IP Address
+    pub fn lookup(&mut self, ip: &str) -> Result<IpDetailsCore, IpError> {
+        self.runtime.block_on(self.inner.lookup(ip))
+    }
+
+    /// looks up IPDetailsCore of your own v4 IP
+    pub fn lookup_self_v4(&mut self) -> Result<IpDetailsCore, IpError> {
+        self.runtime.block_on(self.inner.lookup_self_v4())
+    }
+
+    /// looks up IPDetailsCore of your own v6 IP
+    pub fn lookup_self_v6(&mut self) -> Result<IpDetailsCore, IpError> {
+        self.runtime.block_on(self.inner.lookup_self_v6())
+    }
+
+    /// Snapshot the cached lookup results
+    pub fn export_cache(&self) -> CacheSnapshot<IpDetailsCore> {
+        self.inner.export_cache()
+    }
+
+    /// Preload the cache from a snapshot
+    pub fn import_cache(
+        &mut self,
+        snapshot: CacheSnapshot<IpDetailsCore>,
+    ) -> usize {
+        self.inner.import_cache(snapshot)
+    }
+}
+
+/// Synchronous IpInfoPlus requests context structure.
+pub struct IpInfoPlus {
+    inner: crate::IpInfoPlus,
+    runtime: Runtime,
+}
+
+impl IpInfoPlus {
+    /// Construct a new blocking IpInfoPlus structure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ipinfo::blocking::IpInfoPlus;
+    ///
+    /// let ipinfo = IpInfoPlus::new(Default::default()).expect("should construct");
+    /// ```
+    pub fn new(config: IpInfoPlusConfig) -> Result<Self, IpError> {
+        Ok(Self {
+            inner: crate::IpInfoPlus::new(config)?,
+            runtime: runtime()?,
+        })
+    }
+
+    /// looks up IpDetailsPlus for a single IP Address
+    pub fn lookup(&mut self, ip: &str) -> Result<IpDetailsPlus, IpError> {
+        self.runtime.block_on(self.inner.lookup(ip))
+    }
+
+    /// looks up IPDetailsPlus of your own v4 IP
+    pub fn lookup_self_v4(&mut self) -> Result<IpDetailsPlus, IpError> {
+        self.runtime.block_on(self.inner.lookup_self_v4())
+    }
+
+    /// looks up IPDetailsPlus of your own v6 IP
+    pub fn lookup_self_v6(&mut self) -> Result<IpDetailsPlus, IpError> {
+        self.runtime.block_on(self.inner.lookup_self_v6())
+    }
+
+    /// Snapshot the cached lookup results
+    pub fn export_cache(&self) -> CacheSnapshot<IpDetailsPlus> {
+        self.inner.export_cache()
+    }
+
+    /// Preload the cache from a snapshot
+    pub fn import_cache(
+        &mut self,
+        snapshot: CacheSnapshot<IpDetailsPlus>,
+    ) -> usize {
+        self.inner.import_cache(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn lookup_blocking() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let mock_server = rt.block_on(MockServer::start());
+
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/8.8.8.8"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(
+                    serde_json::json!({
+                        "ip": "8.8.8.8",
+                        "asn": "AS15169",
+                        "as_name": "Google LLC",
+                        "as_domain": "google.com",
+                        "country_code": "US",
+                        "country": "United States"
+                    }),
+                ))
+                .expect(1)
+                .mount(&mock_server),
+        );
+
+        let mut ipinfo = IpInfoLite::new(IpInfoLiteConfig {
+            token: Some("test_token".into()),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        // The second lookup should be served from the cache
+        for _ in 0..2 {
+            let details = ipinfo.lookup("8.8.8.8").expect("should lookup");
+            assert_eq!(details.ip, "8.8.8.8");
+            assert_eq!(details.country_name, "United States");
+            assert_eq!(details.continent.code, "NA");
+        }
+
+        let details = ipinfo.lookup("10.0.0.1").expect("should lookup");
+        assert_eq!(details.bogon, Some(true));
+    }
+}
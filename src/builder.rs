@@ -0,0 +1,295 @@
+//   Copyright 2019-2025 IPinfo library developers
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Fluent builders for the clients.
+//!
+//! Each client has a builder which fills in its configuration and validates
+//! it on `build`, returning a `ConfigError` for a zero cache size or timeout,
+//! or a base URL that is not a valid http(s) URL.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//! use ipinfo::IpInfo;
+//!
+//! let ipinfo = IpInfo::builder()
+//!     .token("my token")
+//!     .timeout(Duration::from_secs(5))
+//!     .cache_size(1000)
+//!     .build()
+//!     .expect("should construct");
+//! ```
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use crate::{
+    CacheMode, CacheTtl, Continent, CountryCurrency, CountryFlag, IpError,
+    IpInfo, IpInfoConfig, IpInfoCore, IpInfoCoreConfig, IpInfoLite,
+    IpInfoLiteConfig, IpInfoPlus, IpInfoPlusConfig, MetricsSink, Middleware,
+    RetryPolicy, SharedCache, Token,
+};
+
+/// Define a builder for a client and its configuration
+macro_rules! client_builder {
+    ($builder:ident, $client:ident, $config:ident) => {
+        #[doc = concat!("Builder for the `", stringify!($client), "` client.")]
+        #[derive(Default)]
+        pub struct $builder {
+            config: $config,
+        }
+
+        impl $client {
+            #[doc = concat!("Create a builder for a new `", stringify!($client), "`.")]
+            pub fn builder() -> $builder {
+                $builder::default()
+            }
+        }
+
+        impl $builder {
+            /// Set the IPinfo access token.
+            pub fn token(mut self, token: impl Into<Token>) -> Self {
+                self.config.token = Some(token.into());
+                self
+            }
+
+            /// Set the timeout of HTTP requests.
+            pub fn timeout(mut self, timeout: Duration) -> Self {
+                self.config.timeout = timeout;
+                self
+            }
+
+            /// Set the HTTP client to send all requests with.
+            pub fn client(mut self, client: reqwest::Client) -> Self {
+                self.config.client = Some(client);
+                self
+            }
+
+            /// Add a middleware, applied after those already added.
+            pub fn middleware(
+                mut self,
+                middleware: Arc<dyn Middleware>,
+            ) -> Self {
+                self.config.middleware.push(middleware);
+                self
+            }
+
+            /// Set the sink receiving request, cache and batch metrics.
+            pub fn metrics(mut self, metrics: Arc<dyn MetricsSink>) -> Self {
+                self.config.metrics = Some(metrics);
+                self
+            }
+
+            /// Set the retry policy for failed requests.
+            pub fn retry(mut self, retry: RetryPolicy) -> Self {
+                self.config.retry = retry;
+                self
+            }
+
+            /// Set the size of the LRU cache.
+            pub fn cache_size(mut self, cache_size: usize) -> Self {
+                self.config.cache_size = cache_size;
+                self
+            }
+
+            /// Set how lookup results are cached.
+            pub fn cache_mode(mut self, cache_mode: CacheMode) -> Self {
+                self.config.cache_mode = cache_mode;
+                self
+            }
+
+            /// Set how long lookup results are cached, and when they are
+            /// refreshed in the background.
+            pub fn cache_ttl(mut self, cache_ttl: CacheTtl) -> Self {
+                self.config.cache_ttl = Some(cache_ttl);
+                self
+            }
+
+            /// Cache failed lookups that would fail again for `ttl`.
+            pub fn negative_cache_ttl(mut self, ttl: Duration) -> Self {
+                self.config.negative_cache_ttl = Some(ttl);
+                self
+            }
+
+            /// Set the mapping of country codes to country names.
+            pub fn default_countries(
+                mut self,
+                countries: HashMap<String, String>,
+            ) -> Self {
+                self.config.default_countries = Some(countries);
+                self
+            }
+
+            /// Set the list of EU countries.
+            pub fn default_eu(mut self, eu: Vec<String>) -> Self {
+                self.config.default_eu = Some(eu);
+                self
+            }
+
+            /// Set the mapping of country codes to their flags.
+            pub fn default_flags(
+                mut self,
+                flags: HashMap<String, CountryFlag>,
+            ) -> Self {
+                self.config.default_flags = Some(flags);
+                self
+            }
+
+            /// Set the mapping of country codes to their currencies.
+            pub fn default_currencies(
+                mut self,
+                currencies: HashMap<String, CountryCurrency>,
+            ) -> Self {
+                self.config.default_currencies = Some(currencies);
+                self
+            }
+
+            /// Set the mapping of country codes to their continents.
+            pub fn default_continents(
+                mut self,
+                continents: HashMap<String, Continent>,
+            ) -> Self {
+                self.config.default_continents = Some(continents);
+                self
+            }
+
+            /// Set the base URL for API requests.
+            pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+                self.config.base_url = Some(base_url.into());
+                self
+            }
+
+            /// Set the base URL for API requests over IPv6.
+            pub fn base_url_v6(
+                mut self,
+                base_url_v6: impl Into<String>,
+            ) -> Self {
+                self.config.base_url_v6 = Some(base_url_v6.into());
+                self
+            }
+
+            /// Validate the configuration and construct the client.
+            pub fn build(self) -> Result<$client, IpError> {
+                $client::new(self.config)
+            }
+
+            /// Validate the configuration and construct the blocking client.
+            #[cfg(feature = "blocking")]
+            pub fn build_blocking(
+                self,
+            ) -> Result<crate::blocking::$client, IpError> {
+                crate::blocking::$client::new(self.config)
+            }
+        }
+    };
+}
+
+client_builder!(IpInfoBuilder, IpInfo, IpInfoConfig);
+client_builder!(IpInfoLiteBuilder, IpInfoLite, IpInfoLiteConfig);
+client_builder!(IpInfoCoreBuilder, IpInfoCore, IpInfoCoreConfig);
+client_builder!(IpInfoPlusBuilder, IpInfoPlus, IpInfoPlusConfig);
+
+/// Add the settings of the tier clients to their builders
+macro_rules! tier_builder {
+    ($builder:ident) => {
+        impl $builder {
+            /// Share the cache with the clients of other tiers.
+            pub fn shared_cache(mut self, shared_cache: SharedCache) -> Self {
+                self.config.shared_cache = Some(shared_cache);
+                self
+            }
+        }
+    };
+}
+
+tier_builder!(IpInfoLiteBuilder);
+tier_builder!(IpInfoCoreBuilder);
+tier_builder!(IpInfoPlusBuilder);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cache_key, CacheEntry, CacheSnapshot, IpDetailsLite, IpErrorKind,
+    };
+    use std::time::SystemTime;
+
+    #[test]
+    fn build_validates_config() {
+        assert!(IpInfo::builder().token("test_token").build().is_ok());
+        assert!(IpInfoLite::builder()
+            .base_url("http://127.0.0.1:8080/lite")
+            .build()
+            .is_ok());
+
+        let errors = [
+            IpInfo::builder().cache_size(0).build().err(),
+            IpInfoLite::builder().timeout(Duration::ZERO).build().err(),
+            IpInfoCore::builder().base_url("not a url").build().err(),
+            IpInfoPlus::builder()
+                .base_url_v6("ftp://v6.ipinfo.io")
+                .build()
+                .err(),
+        ];
+        for err in errors {
+            assert_eq!(err.unwrap().kind(), IpErrorKind::ConfigError);
+        }
+
+        // Plain configurations are validated the same way
+        let err = IpInfo::new(IpInfoConfig {
+            cache_size: 0,
+            ..Default::default()
+        })
+        .err()
+        .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "invalid configuration: cache size must be greater than zero"
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_countries_alias() {
+        let countries = HashMap::from([(
+            "US".to_string(),
+            "Vereinigte Staaten".to_string(),
+        )]);
+        let country_name = |mut ipinfo: IpInfoLite| {
+            let details = IpDetailsLite {
+                ip: "8.8.8.8".to_string(),
+                country_code: "US".to_string(),
+                ..Default::default()
+            };
+            ipinfo.import_cache(CacheSnapshot {
+                entries: vec![CacheEntry {
+                    key: cache_key("8.8.8.8"),
+                    details,
+                    fetched_at: SystemTime::now(),
+                }],
+            });
+            ipinfo.export_cache().entries[0]
+                .details
+                .country_name
+                .clone()
+        };
+
+        let ipinfo = IpInfoLite::new(IpInfoLiteConfig {
+            defaut_countries: Some(countries),
+            ..Default::default()
+        })
+        .expect("should construct");
+        assert_eq!(country_name(ipinfo), "Vereinigte Staaten");
+    }
+}
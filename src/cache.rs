@@ -0,0 +1,1013 @@
+//   Copyright 2019-2025 IPinfo library developers
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Caching of lookup results.
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    future::Future,
+    io::{BufRead, Write},
+    mem,
+    net::IpAddr,
+    num::NonZeroUsize,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use ipnetwork::IpNetwork;
+use lru::LruCache;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use reqwest::RequestBuilder;
+
+use crate::{
+    cache_key, middleware::send, util::cache_key_ip, IpDetails, IpDetailsCore,
+    IpDetailsLite, IpDetailsPlus, IpError, MetricsSink, Middleware,
+    RetryPolicy,
+};
+
+/// How lookup results are cached.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheMode {
+    /// Cache results by IP address.
+    #[default]
+    Address,
+
+    /// Also cache results by the network reported in the response, the AS
+    /// route or abuse network, and serve them for any address within it.
+    ///
+    /// Results served for another address in the network have their
+    /// address-specific fields cleared: the hostname, hosted domains,
+    /// company, carrier or mobile network, and privacy and anonymity
+    /// detection, including the `is_anonymous`, `is_mobile` and
+    /// `is_satellite` flags. Address-specific fields kept in `extra`, such
+    /// as the Core hostname, are removed.
+    Prefix,
+}
+
+impl FromStr for CacheMode {
+    type Err = IpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "address" => Ok(CacheMode::Address),
+            "prefix" => Ok(CacheMode::Prefix),
+            _ => Err(err!(ConfigError, &format!("unknown cache mode {s}"))),
+        }
+    }
+}
+
+/// Expiry of cached lookup results, with stale-while-revalidate.
+///
+/// Results older than `soft` are still returned, and refreshed in the
+/// background. Results older than `hard` are looked up again before
+/// returning.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct CacheTtl {
+    /// Age after which a result is refreshed in the background.
+    pub soft: Duration,
+
+    /// Age after which a result is no longer returned.
+    pub hard: Duration,
+}
+
+/// A cached lookup result.
+pub(crate) enum Cached<V> {
+    Fresh(V),
+    Stale(V),
+}
+
+impl<V> Cached<V> {
+    // Classify a result cached at `cached_at`, or None once it has expired
+    fn new(
+        ttl: Option<CacheTtl>,
+        cached_at: SystemTime,
+        value: V,
+    ) -> Option<Self> {
+        let Some(ttl) = ttl else {
+            return Some(Cached::Fresh(value));
+        };
+        let age = cached_at.elapsed().unwrap_or_default();
+        if age >= ttl.hard {
+            None
+        } else if age >= ttl.soft {
+            Some(Cached::Stale(value))
+        } else {
+            Some(Cached::Fresh(value))
+        }
+    }
+
+    pub(crate) fn is_stale(&self) -> bool {
+        matches!(self, Cached::Stale(_))
+    }
+
+    pub(crate) fn into_inner(self) -> V {
+        match self {
+            Cached::Fresh(value) | Cached::Stale(value) => value,
+        }
+    }
+}
+
+/// Lookup details which can be cached.
+pub(crate) trait Cacheable: Clone {
+    /// The networks the details apply to, as reported by the API.
+    fn networks(&self) -> Vec<&str>;
+
+    /// The details for another address in the same network, keeping only
+    /// the fields which apply to the whole network, such as the location
+    /// and ASN.
+    fn for_address(&self, ip: &str) -> Self;
+}
+
+/// Lookup details which can be restored from a cache snapshot.
+pub(crate) trait Restorable: Cacheable {
+    /// The fields of the response not covered by the struct.
+    fn extra_mut(&mut self) -> &mut HashMap<String, Value>;
+}
+
+// A single field looked up on its own, None if the API has no value for it.
+// Field responses report no network, so they are only cached per address.
+impl Cacheable for Option<String> {
+    fn networks(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    fn for_address(&self, _ip: &str) -> Self {
+        self.clone()
+    }
+}
+
+// Networks reported in the extra fields of the Lite and Core responses
+fn extra_networks(extra: &HashMap<String, Value>) -> Vec<&str> {
+    ["route", "network"]
+        .iter()
+        .filter_map(|key| extra.get(*key)?.as_str())
+        .collect()
+}
+
+// Extra fields which only apply to the looked up address
+const ADDRESS_EXTRA: [&str; 2] = ["ip", "hostname"];
+
+// The extra fields without the address-specific ones
+fn network_extra(extra: &HashMap<String, Value>) -> HashMap<String, Value> {
+    extra
+        .iter()
+        .filter(|(key, _)| !ADDRESS_EXTRA.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+impl Cacheable for IpDetails {
+    fn networks(&self) -> Vec<&str> {
+        let route = self.asn.as_ref().map(|asn| asn.route.as_str());
+        let abuse = self.abuse.as_ref().map(|abuse| abuse.network.as_str());
+        route.into_iter().chain(abuse).collect()
+    }
+
+    fn for_address(&self, ip: &str) -> Self {
+        Self {
+            ip: ip.to_string(),
+            hostname: None,
+            company: None,
+            carrier: None,
+            privacy: None,
+            domains: None,
+            extra: network_extra(&self.extra),
+            ..self.clone()
+        }
+    }
+}
+
+impl Cacheable for IpDetailsLite {
+    fn networks(&self) -> Vec<&str> {
+        extra_networks(&self.extra)
+    }
+
+    fn for_address(&self, ip: &str) -> Self {
+        Self {
+            ip: ip.to_string(),
+            extra: network_extra(&self.extra),
+            ..self.clone()
+        }
+    }
+}
+
+impl Cacheable for IpDetailsCore {
+    fn networks(&self) -> Vec<&str> {
+        extra_networks(&self.extra)
+    }
+
+    fn for_address(&self, ip: &str) -> Self {
+        Self {
+            ip: ip.to_string(),
+            is_anonymous: false,
+            is_mobile: false,
+            is_satellite: false,
+            extra: network_extra(&self.extra),
+            ..self.clone()
+        }
+    }
+}
+
+impl Cacheable for IpDetailsPlus {
+    fn networks(&self) -> Vec<&str> {
+        self.abuse
+            .as_ref()
+            .and_then(|abuse| abuse.network.as_deref())
+            .into_iter()
+            .collect()
+    }
+
+    fn for_address(&self, ip: &str) -> Self {
+        Self {
+            ip: ip.to_string(),
+            hostname: None,
+            mobile: None,
+            anonymous: None,
+            is_anonymous: false,
+            is_mobile: false,
+            is_satellite: false,
+            company: None,
+            privacy: None,
+            domains: None,
+            extra: network_extra(&self.extra),
+            ..self.clone()
+        }
+    }
+}
+
+impl Restorable for IpDetails {
+    fn extra_mut(&mut self) -> &mut HashMap<String, Value> {
+        &mut self.extra
+    }
+}
+
+impl Restorable for IpDetailsLite {
+    fn extra_mut(&mut self) -> &mut HashMap<String, Value> {
+        &mut self.extra
+    }
+}
+
+impl Restorable for IpDetailsCore {
+    fn extra_mut(&mut self) -> &mut HashMap<String, Value> {
+        &mut self.extra
+    }
+}
+
+impl Restorable for IpDetailsPlus {
+    fn extra_mut(&mut self) -> &mut HashMap<String, Value> {
+        &mut self.extra
+    }
+}
+
+/// Results cached by network.
+struct PrefixCache<V> {
+    entries: LruCache<IpNetwork, (SystemTime, V)>,
+
+    // Prefix lengths with cached entries, by IPv4 or IPv6
+    lengths: BTreeSet<(bool, u8)>,
+}
+
+/// Recently failed lookups.
+struct NegativeCache {
+    ttl: Duration,
+    entries: LruCache<String, (Instant, IpError)>,
+}
+
+/// LRU cache of lookup details.
+pub(crate) struct DetailsCache<V> {
+    entries: LruCache<String, (SystemTime, V)>,
+    prefixes: Option<PrefixCache<V>>,
+    failures: Option<NegativeCache>,
+    ttl: Option<CacheTtl>,
+}
+
+impl<V: Cacheable> DetailsCache<V> {
+    pub(crate) fn new(
+        capacity: NonZeroUsize,
+        mode: CacheMode,
+        ttl: Option<CacheTtl>,
+        negative_ttl: Option<Duration>,
+    ) -> Self {
+        Self {
+            entries: LruCache::new(capacity),
+            prefixes: (mode == CacheMode::Prefix).then(|| PrefixCache {
+                entries: LruCache::new(capacity),
+                lengths: BTreeSet::new(),
+            }),
+            failures: negative_ttl.map(|ttl| NegativeCache {
+                ttl,
+                entries: LruCache::new(capacity),
+            }),
+            ttl,
+        }
+    }
+
+    /// Get the cached details of an address.
+    pub(crate) fn get(&mut self, ip: &str) -> Option<Cached<V>> {
+        let key = cache_key(ip);
+        if let Some((cached_at, details)) = self.entries.get(&key) {
+            match Cached::new(self.ttl, *cached_at, details.clone()) {
+                Some(cached) => return Some(cached),
+                None => {
+                    self.entries.pop(&key);
+                }
+            }
+        }
+
+        let prefixes = self.prefixes.as_mut()?;
+        let addr: IpAddr = ip.parse().ok()?;
+        // Prefer the most specific network
+        for &(_, len) in prefixes
+            .lengths
+            .iter()
+            .rev()
+            .filter(|(v4, _)| *v4 == addr.is_ipv4())
+        {
+            let Some(network) = IpNetwork::new(addr, len)
+                .and_then(|net| IpNetwork::new(net.network(), len))
+                .ok()
+            else {
+                continue;
+            };
+            if let Some((cached_at, details)) = prefixes.entries.get(&network)
+            {
+                let details = details.for_address(ip);
+                if let Some(cached) =
+                    Cached::new(self.ttl, *cached_at, details)
+                {
+                    return Some(cached);
+                }
+            }
+        }
+        None
+    }
+
+    /// Cache the details of an address.
+    pub(crate) fn put(&mut self, ip: &str, details: V) {
+        self.put_at(ip, details, SystemTime::now());
+    }
+
+    /// Cache the details of an address fetched at the given time.
+    pub(crate) fn put_at(
+        &mut self,
+        ip: &str,
+        details: V,
+        fetched_at: SystemTime,
+    ) {
+        if let Some(prefixes) = self.prefixes.as_mut() {
+            // The most specific reported network containing the address
+            let network = ip.parse::<IpAddr>().ok().and_then(|addr| {
+                details
+                    .networks()
+                    .into_iter()
+                    .filter_map(|net| net.parse::<IpNetwork>().ok())
+                    .filter(|net| net.contains(addr))
+                    .max_by_key(|net| net.prefix())
+            });
+            if let Some(net) = network {
+                let network = IpNetwork::new(net.network(), net.prefix())
+                    .expect("prefix of a valid network");
+                prefixes
+                    .lengths
+                    .insert((network.is_ipv4(), network.prefix()));
+                prefixes.entries.put(network, (fetched_at, details.clone()));
+            }
+        }
+        self.entries.put(cache_key(ip), (fetched_at, details));
+    }
+
+    /// Get the error of a recent failed lookup of an address.
+    pub(crate) fn get_error(&mut self, ip: &str) -> Option<IpError> {
+        let failures = self.failures.as_mut()?;
+        let key = cache_key(ip);
+        let (failed_at, error) = failures.entries.get(&key)?;
+        if failed_at.elapsed() < failures.ttl {
+            return Some(error.clone());
+        }
+        failures.entries.pop(&key);
+        None
+    }
+
+    /// Cache the error of a failed lookup, if it would fail again.
+    pub(crate) fn put_error(&mut self, ip: &str, error: &IpError) {
+        if let Some(failures) = self.failures.as_mut() {
+            if error.is_deterministic() {
+                failures
+                    .entries
+                    .put(cache_key(ip), (Instant::now(), error.clone()));
+            }
+        }
+    }
+
+    /// Snapshot the cached details, least recently used first. Lookups of
+    /// the client's own IP are left out.
+    pub(crate) fn snapshot(&self) -> CacheSnapshot<V> {
+        let entries = self
+            .entries
+            .iter()
+            .rev()
+            .filter(|(key, _)| !cache_key_ip(key).is_some_and(is_own_address))
+            .map(|(key, (fetched_at, details))| CacheEntry {
+                key: key.clone(),
+                details: details.clone(),
+                fetched_at: *fetched_at,
+            })
+            .collect();
+        CacheSnapshot { entries }
+    }
+
+    /// Cache the entries of a snapshot, returning the number cached.
+    /// Entries from another version of the cache, and of the exporting
+    /// client's own address, are skipped.
+    pub(crate) fn import(&mut self, entries: Vec<CacheEntry<V>>) -> usize {
+        let mut count = 0;
+        for entry in entries {
+            let ip = cache_key_ip(&entry.key);
+            if let Some(ip) = ip.filter(|ip| !is_own_address(ip)) {
+                self.put_at(ip, entry.details, entry.fetched_at);
+                count += 1;
+            }
+        }
+        count
+    }
+}
+
+// Whether a cache key names the client's own address rather than an IP
+// address: "me" for the tier clients and "" for `IpInfo`
+fn is_own_address(ip: &str) -> bool {
+    matches!(ip, "me" | "")
+}
+
+/// A cached lookup result in a `CacheSnapshot`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry<V> {
+    /// The cache key of the result.
+    pub key: String,
+
+    /// The lookup result.
+    pub details: V,
+
+    /// When the result was fetched, in milliseconds since the Unix epoch in
+    /// JSON.
+    #[serde(with = "unix_millis")]
+    pub fetched_at: SystemTime,
+}
+
+/// A snapshot of the cached lookup results of a client, to start another
+/// client with a warm cache.
+///
+/// Snapshots are written and read as JSON lines, one entry per line.
+#[derive(Debug, Clone)]
+pub struct CacheSnapshot<V> {
+    /// The cached results, least recently used first.
+    pub entries: Vec<CacheEntry<V>>,
+}
+
+impl<V> Default for CacheSnapshot<V> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<V: Serialize> CacheSnapshot<V> {
+    /// Write the snapshot as JSON lines.
+    pub fn write_json_lines(
+        &self,
+        mut writer: impl Write,
+    ) -> Result<(), IpError> {
+        for entry in &self.entries {
+            serde_json::to_writer(&mut writer, entry)?;
+            writeln!(writer).map_err(|e| err!(ParseError, &e.to_string()))?;
+        }
+        writer.flush().map_err(|e| err!(ParseError, &e.to_string()))
+    }
+}
+
+impl<V: DeserializeOwned> CacheSnapshot<V> {
+    /// Read a snapshot from JSON lines.
+    pub fn read_json_lines(reader: impl BufRead) -> Result<Self, IpError> {
+        let mut entries = Vec::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| err!(ParseError, &e.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(&line).map_err(|e| {
+                err!(ParseError, &format!("line {}: {e}", i + 1))
+            })?;
+            entries.push(entry);
+        }
+        Ok(Self { entries })
+    }
+}
+
+/// The entries of a snapshot to import, with the fields the client adds to
+/// responses removed from the extra fields they deserialize into.
+pub(crate) fn restored_entries<V>(
+    snapshot: CacheSnapshot<V>,
+) -> Vec<CacheEntry<V>>
+where
+    V: Restorable + Default + Serialize,
+{
+    let fields: HashSet<String> = match serde_json::to_value(V::default()) {
+        Ok(Value::Object(fields)) => {
+            fields.into_iter().map(|(k, _)| k).collect()
+        }
+        _ => HashSet::new(),
+    };
+    snapshot
+        .entries
+        .into_iter()
+        .map(|mut entry| {
+            entry
+                .details
+                .extra_mut()
+                .retain(|key, _| !fields.contains(key));
+            entry
+        })
+        .collect()
+}
+
+/// Serialize times as integer milliseconds since the Unix epoch.
+mod unix_millis {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(
+        time: &SystemTime,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let millis = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        serializer.serialize_u64(millis as u64)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SystemTime, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        u64::deserialize(deserializer)
+            .map(|millis| UNIX_EPOCH + Duration::from_millis(millis))
+    }
+}
+
+/// A completed background refresh: the address and the raw response.
+type Refreshed = (String, Result<String, IpError>);
+
+/// A finished background refresh, with no response if it panicked or was
+/// dropped before completing.
+type Finished = (String, Option<Result<String, IpError>>);
+
+/// Reports a background refresh as finished when dropped, so its address
+/// is refreshed again later even if the task panics or its runtime shuts
+/// down before running it.
+struct Completion {
+    ip: String,
+    result: Option<Result<String, IpError>>,
+    done: Arc<Mutex<Vec<Finished>>>,
+}
+
+impl Completion {
+    fn finish(&mut self, result: Result<String, IpError>) {
+        self.result = Some(result);
+    }
+}
+
+impl Drop for Completion {
+    fn drop(&mut self) {
+        let mut done = self.done.lock().unwrap_or_else(|e| e.into_inner());
+        done.push((mem::take(&mut self.ip), self.result.take()));
+    }
+}
+
+/// Background refreshes of stale cache entries.
+///
+/// Refreshes only fetch the response, which the client parses and caches
+/// on its next lookup.
+pub(crate) struct Refreshes {
+    client: reqwest::Client,
+    middleware: Vec<Arc<dyn Middleware>>,
+    metrics: Option<Arc<dyn MetricsSink>>,
+    retry: RetryPolicy,
+    endpoint: &'static str,
+    in_flight: HashSet<String>,
+    done: Arc<Mutex<Vec<Finished>>>,
+}
+
+impl Refreshes {
+    /// Refreshes sent like the lookups of a client, recorded in the metrics
+    /// under its endpoint.
+    pub(crate) fn new(
+        client: &reqwest::Client,
+        middleware: &[Arc<dyn Middleware>],
+        metrics: &Option<Arc<dyn MetricsSink>>,
+        retry: &RetryPolicy,
+        endpoint: &'static str,
+    ) -> Self {
+        Self {
+            client: client.clone(),
+            middleware: middleware.to_vec(),
+            metrics: metrics.clone(),
+            retry: retry.clone(),
+            endpoint,
+            in_flight: HashSet::new(),
+            done: Arc::default(),
+        }
+    }
+
+    /// Whether a refresh of the address is in flight, so a client can skip
+    /// building its request.
+    pub(crate) fn in_flight(&self, ip: &str) -> bool {
+        self.in_flight.contains(ip)
+    }
+
+    /// Refresh the cached details of an address in the background unless a
+    /// refresh is already in flight. Returns false if there is no runtime
+    /// to spawn it on.
+    pub(crate) fn refresh(
+        &mut self,
+        ip: &str,
+        request: RequestBuilder,
+    ) -> bool {
+        event!(debug, "refreshing stale cache entry");
+        let client = self.client.clone();
+        let middleware = self.middleware.clone();
+        let metrics = self.metrics.clone();
+        let retry = self.retry.clone();
+        let endpoint = self.endpoint;
+        self.spawn(ip, async move {
+            send(
+                &client,
+                &middleware,
+                metrics.as_deref(),
+                &retry,
+                endpoint,
+                request,
+            )
+            .await
+        })
+    }
+
+    /// Take the details of the completed refreshes, parsed by `parse`.
+    /// Failed refreshes are logged and skipped.
+    pub(crate) fn completed<V: Cacheable>(
+        &mut self,
+        parse: impl Fn(&str) -> Result<V, IpError>,
+    ) -> Vec<(String, V)> {
+        self.finished()
+            .into_iter()
+            .filter_map(|(ip, raw_resp)| {
+                match raw_resp.and_then(|raw_resp| parse(&raw_resp)) {
+                    Ok(details) => Some((ip, details)),
+                    Err(_e) => {
+                        event!(
+                            warn,
+                            ip = %ip,
+                            error = %_e,
+                            "failed to refresh cache entry"
+                        );
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    // Spawn a request unless one is already in flight for the address
+    fn spawn<F>(&mut self, ip: &str, request: F) -> bool
+    where
+        F: Future<Output = Result<String, IpError>> + Send + 'static,
+    {
+        if self.in_flight.contains(ip) {
+            return true;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return false;
+        };
+
+        self.in_flight.insert(ip.to_string());
+        let mut completion = Completion {
+            ip: ip.to_string(),
+            result: None,
+            done: self.done.clone(),
+        };
+        runtime.spawn(async move {
+            completion.finish(request.await);
+        });
+        true
+    }
+
+    // Take the finished refreshes, keeping the responses of completed ones
+    fn finished(&mut self) -> Vec<Refreshed> {
+        let done = mem::take(
+            &mut *self.done.lock().unwrap_or_else(|e| e.into_inner()),
+        );
+        done.into_iter()
+            .filter_map(|(ip, result)| {
+                self.in_flight.remove(&ip);
+                if result.is_none() {
+                    event!(warn, ip = %ip, "cache refresh did not complete");
+                }
+                Some((ip, result?))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AbuseDetails, AsnDetails, CoreAS, PlusAbuse, PlusAnonymous,
+        PlusPrivacy, PrivacyDetails,
+    };
+
+    fn details(ip: &str, route: &str, abuse: &str) -> IpDetails {
+        IpDetails {
+            ip: ip.to_string(),
+            hostname: Some("dns.google".to_string()),
+            city: "Mountain View".to_string(),
+            asn: Some(AsnDetails {
+                asn: "AS15169".to_string(),
+                name: "Google LLC".to_string(),
+                domain: "google.com".to_string(),
+                route: route.to_string(),
+                asn_type: "hosting".to_string(),
+            }),
+            abuse: Some(AbuseDetails {
+                address: String::new(),
+                country: "US".to_string(),
+                email: "network-abuse@google.com".to_string(),
+                name: "Abuse".to_string(),
+                network: abuse.to_string(),
+                phone: String::new(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn prefix_cache() {
+        let capacity = NonZeroUsize::new(10).unwrap();
+        let mut cache =
+            DetailsCache::new(capacity, CacheMode::Prefix, None, None);
+        cache.put("8.8.8.8", details("8.8.8.8", "8.8.8.0/24", "8.8.0.0/16"));
+
+        let hit = cache
+            .get("8.8.8.100")
+            .expect("should hit by prefix")
+            .into_inner();
+        assert_eq!(hit.ip, "8.8.8.100");
+        assert_eq!(hit.city, "Mountain View");
+        assert_eq!(hit.hostname, None);
+
+        // Exact hits keep the address-specific fields
+        let hit = cache
+            .get("8.8.8.8")
+            .expect("should hit by address")
+            .into_inner();
+        assert_eq!(hit.hostname.as_deref(), Some("dns.google"));
+
+        // The route is more specific than the abuse network
+        assert!(cache.get("8.8.9.1").is_none());
+
+        // Networks not containing the address are ignored
+        cache.put("1.1.1.1", details("1.1.1.1", "9.9.9.0/24", ""));
+        assert!(cache.get("9.9.9.9").is_none());
+        assert!(cache.get("1.1.1.2").is_none());
+
+        cache.put(
+            "2001:4860::8888",
+            details("2001:4860::8888", "2001:4860::/32", ""),
+        );
+        assert!(cache.get("2001:4860:1::1").is_some());
+
+        let mut cache =
+            DetailsCache::new(capacity, CacheMode::Address, None, None);
+        cache.put("8.8.8.8", details("8.8.8.8", "8.8.8.0/24", ""));
+        assert!(cache.get("8.8.8.8").is_some());
+        assert!(cache.get("8.8.8.100").is_none());
+    }
+
+    #[test]
+    fn prefix_cache_clears_address_fields() {
+        let capacity = NonZeroUsize::new(10).unwrap();
+        let mut cache =
+            DetailsCache::new(capacity, CacheMode::Prefix, None, None);
+        cache.put(
+            "8.8.8.8",
+            IpDetails {
+                privacy: Some(PrivacyDetails {
+                    vpn: true,
+                    proxy: false,
+                    tor: false,
+                    relay: false,
+                    hosting: false,
+                    service: "NordVPN".to_string(),
+                }),
+                ..details("8.8.8.8", "8.8.8.0/24", "")
+            },
+        );
+
+        // A VPN exit does not make its neighbours read as VPN
+        let hit = cache.get("8.8.8.9").unwrap().into_inner();
+        assert_eq!(hit.city, "Mountain View");
+        assert!(hit.privacy.is_none());
+        let hit = cache.get("8.8.8.8").unwrap().into_inner();
+        assert!(hit.privacy.unwrap().vpn);
+
+        let mut cache =
+            DetailsCache::new(capacity, CacheMode::Prefix, None, None);
+        let mut extra = HashMap::new();
+        extra.insert("network".to_string(), Value::from("8.8.8.0/24"));
+        extra.insert("hostname".to_string(), Value::from("dns.google"));
+        cache.put(
+            "8.8.8.8",
+            IpDetailsCore {
+                asn: Some(CoreAS {
+                    asn: "AS15169".to_string(),
+                    ..Default::default()
+                }),
+                is_anonymous: true,
+                is_anycast: true,
+                is_mobile: true,
+                extra: extra.clone(),
+                ..Default::default()
+            },
+        );
+        let hit = cache.get("8.8.8.9").unwrap().into_inner();
+        assert_eq!(hit.asn.unwrap().asn, "AS15169");
+        assert!(hit.is_anycast);
+        assert!(!hit.is_anonymous);
+        assert!(!hit.is_mobile);
+        assert_eq!(hit.extra["network"], "8.8.8.0/24");
+        assert!(!hit.extra.contains_key("hostname"));
+        let hit = cache.get("8.8.8.8").unwrap().into_inner();
+        assert_eq!(hit.extra["hostname"], "dns.google");
+
+        let mut cache =
+            DetailsCache::new(capacity, CacheMode::Prefix, None, None);
+        cache.put(
+            "8.8.8.8",
+            IpDetailsPlus {
+                anonymous: Some(PlusAnonymous {
+                    is_vpn: true,
+                    ..Default::default()
+                }),
+                privacy: Some(PlusPrivacy {
+                    vpn: true,
+                    ..Default::default()
+                }),
+                is_anonymous: true,
+                abuse: Some(PlusAbuse {
+                    network: Some("8.8.8.0/24".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+        let hit = cache.get("8.8.8.9").unwrap().into_inner();
+        assert!(hit.abuse.is_some());
+        assert!(hit.anonymous.is_none());
+        assert!(hit.privacy.is_none());
+        assert!(!hit.is_anonymous);
+    }
+
+    #[test]
+    fn negative_cache() {
+        let capacity = NonZeroUsize::new(10).unwrap();
+        let ttl = Duration::from_millis(50);
+        let mut cache: DetailsCache<IpDetails> =
+            DetailsCache::new(capacity, CacheMode::Address, None, Some(ttl));
+
+        cache.put_error("8.8.8.8", &err!(IpRequestError, "Wrong ip"));
+        cache.put_error("4.2.2.4", &err!(RateLimitExceededError));
+        assert_eq!(
+            cache.get_error("8.8.8.8"),
+            Some(err!(IpRequestError, "Wrong ip"))
+        );
+        assert!(cache.get_error("4.2.2.4").is_none());
+
+        std::thread::sleep(ttl);
+        assert!(cache.get_error("8.8.8.8").is_none());
+
+        let mut cache: DetailsCache<IpDetails> =
+            DetailsCache::new(capacity, CacheMode::Address, None, None);
+        cache.put_error("8.8.8.8", &err!(IpRequestError, "Wrong ip"));
+        assert!(cache.get_error("8.8.8.8").is_none());
+    }
+
+    #[test]
+    fn refreshes_finish_without_result() {
+        let mut refreshes = Refreshes::new(
+            &reqwest::Client::new(),
+            &[],
+            &None,
+            &RetryPolicy::default(),
+            "lookup",
+        );
+
+        // A panicking refresh
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            assert!(refreshes.spawn("8.8.8.8", async { panic!("refresh") }));
+            while refreshes
+                .done
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .is_empty()
+            {
+                tokio::task::yield_now().await;
+            }
+        });
+        assert!(refreshes.finished().is_empty());
+        assert!(refreshes.in_flight.is_empty());
+
+        // A refresh dropped with its runtime before running
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            assert!(refreshes.spawn("8.8.8.8", async { Ok(String::new()) }));
+        });
+        assert!(refreshes.in_flight.contains("8.8.8.8"));
+        drop(runtime);
+        assert!(refreshes.finished().is_empty());
+        assert!(refreshes.in_flight.is_empty());
+
+        // Completed refreshes return their response
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            assert!(refreshes.spawn("8.8.8.8", async { Ok("{}".to_string()) }));
+            tokio::task::yield_now().await;
+        });
+        let finished = refreshes.finished();
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].1, Ok("{}".to_string()));
+        assert!(!refreshes.spawn("8.8.8.8", async { Ok(String::new()) }));
+
+        // A poisoned lock still returns the finished refreshes
+        let done = refreshes.done.clone();
+        let _ = std::thread::spawn(move || {
+            let _lock = done.lock().unwrap();
+            panic!("poison");
+        })
+        .join();
+        assert!(refreshes.done.is_poisoned());
+        refreshes.in_flight.insert("8.8.8.8".to_string());
+        refreshes
+            .done
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(("8.8.8.8".to_string(), Some(Ok("{}".to_string()))));
+        assert_eq!(refreshes.finished().len(), 1);
+        assert!(!refreshes.in_flight("8.8.8.8"));
+    }
+
+    #[test]
+    fn cache_ttl() {
+        let capacity = NonZeroUsize::new(10).unwrap();
+        let ttl = CacheTtl {
+            soft: Duration::from_millis(20),
+            hard: Duration::from_millis(60),
+        };
+        let mut cache =
+            DetailsCache::new(capacity, CacheMode::Prefix, Some(ttl), None);
+        cache.put("8.8.8.8", details("8.8.8.8", "8.8.8.0/24", ""));
+
+        assert!(!cache.get("8.8.8.8").unwrap().is_stale());
+        std::thread::sleep(ttl.soft);
+        assert!(cache.get("8.8.8.8").unwrap().is_stale());
+        assert!(cache.get("8.8.8.1").unwrap().is_stale());
+        std::thread::sleep(ttl.hard - ttl.soft);
+        assert!(cache.get("8.8.8.8").is_none());
+        assert!(cache.get("8.8.8.1").is_none());
+    }
+}
@@ -0,0 +1,439 @@
+//   Copyright 2019-2025 IPinfo library developers
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Conversions between the details of different tiers.
+//!
+//! Richer details convert into poorer ones without losing any field the
+//! poorer type has: Plus into Core, Core into Lite, and any tier into the
+//! standard `IpDetails`. The shared cache and the local databases use these
+//! conversions rather than their own.
+
+use crate::{
+    AbuseDetails, AsnDetails, CarrierDetails, CompanyDetails, Continent,
+    CoreAS, CoreGeo, DomainsDetails, IpDetails, IpDetailsCommon,
+    IpDetailsCore, IpDetailsLite, IpDetailsPlus, PrivacyDetails,
+};
+
+// A string, unless empty
+fn non_empty(value: &str) -> Option<String> {
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+// The continent of geo details: the added continent details, or else the
+// continent code and name of the response
+fn continent(
+    info: Option<Continent>,
+    code: Option<String>,
+    name: Option<String>,
+) -> Option<Continent> {
+    info.or_else(|| {
+        Some(Continent {
+            code: code?,
+            name: name.unwrap_or_default(),
+        })
+    })
+}
+
+impl From<IpDetailsPlus> for IpDetailsCore {
+    fn from(details: IpDetailsPlus) -> Self {
+        IpDetailsCore {
+            ip: details.ip,
+            geo: details.geo.map(|geo| CoreGeo {
+                city: geo.city,
+                region: geo.region,
+                region_code: geo.region_code,
+                country: geo.country,
+                country_code: geo.country_code,
+                continent: geo.continent,
+                continent_code: geo.continent_code,
+                latitude: geo.latitude,
+                longitude: geo.longitude,
+                timezone: geo.timezone,
+                postal_code: geo.postal_code,
+                country_name: geo.country_name,
+                is_eu: geo.is_eu,
+                country_flag: geo.country_flag,
+                country_flag_url: geo.country_flag_url,
+                country_currency: geo.country_currency,
+                continent_info: geo.continent_info,
+            }),
+            asn: details.asn.map(|asn| CoreAS {
+                asn: asn.asn,
+                name: asn.name,
+                domain: asn.domain,
+                as_type: asn.as_type,
+            }),
+            is_anonymous: details.is_anonymous,
+            is_anycast: details.is_anycast,
+            is_hosting: details.is_hosting,
+            is_mobile: details.is_mobile,
+            is_satellite: details.is_satellite,
+            bogon: details.bogon,
+            extra: details.extra,
+        }
+    }
+}
+
+impl From<IpDetailsCore> for IpDetailsLite {
+    fn from(details: IpDetailsCore) -> Self {
+        let geo = details.geo.unwrap_or_default();
+        let asn = details.asn.unwrap_or_default();
+        IpDetailsLite {
+            ip: details.ip,
+            country_code: geo.country_code.unwrap_or_default(),
+            country: geo.country.unwrap_or_default(),
+            country_name: geo.country_name.unwrap_or_default(),
+            is_eu: geo.is_eu.unwrap_or_default(),
+            country_flag: geo.country_flag.unwrap_or_default(),
+            country_flag_url: geo.country_flag_url.unwrap_or_default(),
+            country_currency: geo.country_currency.unwrap_or_default(),
+            asn: asn.asn,
+            as_name: asn.name,
+            as_domain: asn.domain,
+            continent: continent(
+                geo.continent_info,
+                geo.continent_code,
+                geo.continent,
+            )
+            .unwrap_or_default(),
+            bogon: details.bogon,
+            extra: details.extra,
+        }
+    }
+}
+
+impl From<IpDetailsPlus> for IpDetailsLite {
+    fn from(details: IpDetailsPlus) -> Self {
+        IpDetailsCore::from(details).into()
+    }
+}
+
+impl From<IpDetailsLite> for IpDetails {
+    fn from(lite: IpDetailsLite) -> Self {
+        let is_set = !lite.country_code.is_empty();
+        let org = (!lite.asn.is_empty())
+            .then(|| format!("{} {}", lite.asn, lite.as_name));
+        let asn = (!lite.asn.is_empty()).then(|| AsnDetails {
+            asn: lite.asn.clone(),
+            name: lite.as_name.clone(),
+            domain: lite.as_domain.clone(),
+            route: String::new(),
+            asn_type: String::new(),
+        });
+        IpDetails {
+            ip: lite.ip,
+            country: lite.country_code,
+            country_name: is_set.then_some(lite.country_name),
+            is_eu: is_set.then_some(lite.is_eu),
+            country_flag: is_set.then_some(lite.country_flag),
+            country_flag_url: is_set.then_some(lite.country_flag_url),
+            country_currency: is_set.then_some(lite.country_currency),
+            continent: is_set.then_some(lite.continent),
+            org,
+            asn,
+            bogon: lite.bogon,
+            extra: lite.extra,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<IpDetailsCore> for IpDetails {
+    fn from(core: IpDetailsCore) -> Self {
+        let mut details = IpDetails {
+            ip: core.ip,
+            bogon: core.bogon,
+            extra: core.extra,
+            ..Default::default()
+        };
+        if let Some(geo) = core.geo {
+            if let Some(coordinates) = geo.coordinates() {
+                details.loc = coordinates.to_string();
+            }
+            details.city = geo.city.unwrap_or_default();
+            details.region = geo.region.unwrap_or_default();
+            details.country = geo.country_code.unwrap_or_default();
+            details.country_name = geo.country_name;
+            details.is_eu = geo.is_eu;
+            details.country_flag = geo.country_flag;
+            details.country_flag_url = geo.country_flag_url;
+            details.country_currency = geo.country_currency;
+            details.continent = continent(
+                geo.continent_info,
+                geo.continent_code,
+                geo.continent,
+            );
+            details.postal = geo.postal_code;
+            details.timezone = geo.timezone;
+        }
+        if let Some(asn) = core.asn {
+            details.org = Some(format!("{} {}", asn.asn, asn.name));
+            details.asn = Some(AsnDetails {
+                asn: asn.asn,
+                name: asn.name,
+                domain: asn.domain,
+                route: String::new(),
+                asn_type: asn.as_type,
+            });
+        }
+        details
+    }
+}
+
+impl From<IpDetailsPlus> for IpDetails {
+    fn from(plus: IpDetailsPlus) -> Self {
+        let hostname = plus.hostname.clone();
+        let mobile = plus.mobile.clone();
+        let company = plus.company.clone();
+        let privacy = plus.privacy.clone();
+        let abuse = plus.abuse.clone();
+        let domains = plus.domains.clone();
+
+        let mut details = IpDetails::from(IpDetailsCore::from(plus));
+        details.hostname = hostname;
+        details.carrier = mobile.map(|mobile| CarrierDetails {
+            name: mobile.name.unwrap_or_default(),
+            mcc: mobile.mcc.unwrap_or_default(),
+            mnc: mobile.mnc.unwrap_or_default(),
+        });
+        details.company = company.map(|company| CompanyDetails {
+            name: company.name.unwrap_or_default(),
+            domain: company.domain.unwrap_or_default(),
+            company_type: company.company_type.unwrap_or_default(),
+        });
+        details.privacy = privacy.map(|privacy| PrivacyDetails {
+            vpn: privacy.vpn,
+            proxy: privacy.proxy,
+            tor: privacy.tor,
+            relay: privacy.relay,
+            hosting: privacy.hosting,
+            service: privacy.service.unwrap_or_default(),
+        });
+        details.abuse = abuse.map(|abuse| AbuseDetails {
+            address: abuse.address.unwrap_or_default(),
+            country: abuse.country.unwrap_or_default(),
+            email: abuse.email.unwrap_or_default(),
+            name: abuse.name.unwrap_or_default(),
+            network: abuse.network.unwrap_or_default(),
+            phone: abuse.phone.unwrap_or_default(),
+        });
+        details.domains = domains.map(|domains| DomainsDetails {
+            ip: domains.ip,
+            total: domains.total,
+            domains: domains.domains,
+        });
+        details
+    }
+}
+
+impl From<&IpDetails> for IpDetailsCommon {
+    fn from(details: &IpDetails) -> Self {
+        let asn = details.asn.as_ref();
+        IpDetailsCommon {
+            ip: details.ip.clone(),
+            hostname: details.hostname.clone(),
+            city: non_empty(&details.city),
+            region: non_empty(&details.region),
+            country_code: non_empty(&details.country),
+            country_name: details.country_name.clone(),
+            continent: details.continent.clone(),
+            coordinates: details.coordinates(),
+            postal_code: details.postal.clone(),
+            timezone: details.timezone.clone(),
+            asn: asn.map(|asn| asn.asn.clone()),
+            as_name: asn.map(|asn| asn.name.clone()),
+            as_domain: asn.map(|asn| asn.domain.clone()),
+            is_hosting: details.privacy.as_ref().map(|p| p.hosting),
+            is_anonymous: details
+                .privacy
+                .as_ref()
+                .map(|p| p.vpn || p.proxy || p.relay || p.tor),
+            bogon: details.bogon,
+        }
+    }
+}
+
+impl From<&IpDetailsLite> for IpDetailsCommon {
+    fn from(details: &IpDetailsLite) -> Self {
+        let is_set = !details.country_code.is_empty();
+        IpDetailsCommon {
+            ip: details.ip.clone(),
+            country_code: non_empty(&details.country_code),
+            country_name: is_set.then(|| details.country_name.clone()),
+            continent: is_set.then(|| details.continent.clone()),
+            asn: non_empty(&details.asn),
+            as_name: non_empty(&details.as_name),
+            as_domain: non_empty(&details.as_domain),
+            bogon: details.bogon,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&IpDetailsCore> for IpDetailsCommon {
+    fn from(details: &IpDetailsCore) -> Self {
+        let geo = details.geo.as_ref();
+        let asn = details.asn.as_ref();
+        IpDetailsCommon {
+            ip: details.ip.clone(),
+            hostname: None,
+            city: geo.and_then(|geo| geo.city.clone()),
+            region: geo.and_then(|geo| geo.region.clone()),
+            country_code: geo.and_then(|geo| geo.country_code.clone()),
+            country_name: geo.and_then(|geo| geo.country_name.clone()),
+            continent: geo.and_then(|geo| {
+                continent(
+                    geo.continent_info.clone(),
+                    geo.continent_code.clone(),
+                    geo.continent.clone(),
+                )
+            }),
+            coordinates: details.coordinates(),
+            postal_code: geo.and_then(|geo| geo.postal_code.clone()),
+            timezone: geo.and_then(|geo| geo.timezone.clone()),
+            asn: asn.map(|asn| asn.asn.clone()),
+            as_name: asn.map(|asn| asn.name.clone()),
+            as_domain: asn.map(|asn| asn.domain.clone()),
+            is_hosting: Some(details.is_hosting),
+            is_anonymous: Some(details.is_anonymous),
+            bogon: details.bogon,
+        }
+    }
+}
+
+impl From<&IpDetailsPlus> for IpDetailsCommon {
+    fn from(details: &IpDetailsPlus) -> Self {
+        let geo = details.geo.as_ref();
+        let asn = details.asn.as_ref();
+        IpDetailsCommon {
+            ip: details.ip.clone(),
+            hostname: details.hostname.clone(),
+            city: geo.and_then(|geo| geo.city.clone()),
+            region: geo.and_then(|geo| geo.region.clone()),
+            country_code: geo.and_then(|geo| geo.country_code.clone()),
+            country_name: geo.and_then(|geo| geo.country_name.clone()),
+            continent: geo.and_then(|geo| {
+                continent(
+                    geo.continent_info.clone(),
+                    geo.continent_code.clone(),
+                    geo.continent.clone(),
+                )
+            }),
+            coordinates: details.coordinates(),
+            postal_code: geo.and_then(|geo| geo.postal_code.clone()),
+            timezone: geo.and_then(|geo| geo.timezone.clone()),
+            asn: asn.map(|asn| asn.asn.clone()),
+            as_name: asn.map(|asn| asn.name.clone()),
+            as_domain: asn.map(|asn| asn.domain.clone()),
+            is_hosting: Some(details.is_hosting),
+            is_anonymous: Some(details.is_anonymous),
+            bogon: details.bogon,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PlusAS, PlusGeo, PlusMobile, PlusPrivacy};
+
+    fn plus_details() -> IpDetailsPlus {
+        IpDetailsPlus {
+            ip: "8.8.8.8".to_string(),
+            hostname: Some("dns.google".to_string()),
+            geo: Some(PlusGeo {
+                city: Some("Mountain View".to_string()),
+                region: Some("California".to_string()),
+                country: Some("United States".to_string()),
+                country_code: Some("US".to_string()),
+                continent: Some("North America".to_string()),
+                continent_code: Some("NA".to_string()),
+                latitude: 37.4056,
+                longitude: -122.0775,
+                postal_code: Some("94043".to_string()),
+                ..Default::default()
+            }),
+            asn: Some(PlusAS {
+                asn: "AS15169".to_string(),
+                name: "Google LLC".to_string(),
+                domain: "google.com".to_string(),
+                as_type: "hosting".to_string(),
+                last_changed: None,
+            }),
+            mobile: Some(PlusMobile {
+                name: Some("Carrier".to_string()),
+                mcc: Some("310".to_string()),
+                mnc: None,
+            }),
+            privacy: Some(PlusPrivacy {
+                hosting: true,
+                ..Default::default()
+            }),
+            is_anycast: true,
+            is_hosting: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn convert_down_tiers() {
+        let core = IpDetailsCore::from(plus_details());
+        assert_eq!(core.geo.as_ref().unwrap().region_code, None);
+        assert_eq!(core.asn.as_ref().unwrap().as_type, "hosting");
+        assert!(core.is_anycast);
+
+        let lite = IpDetailsLite::from(plus_details());
+        assert_eq!(lite.country_code, "US");
+        assert_eq!(lite.continent.code, "NA");
+        assert_eq!(lite.as_domain, "google.com");
+
+        let details = IpDetails::from(plus_details());
+        assert_eq!(details.hostname.as_deref(), Some("dns.google"));
+        assert_eq!(details.city, "Mountain View");
+        assert_eq!(details.country, "US");
+        assert_eq!(details.loc, "37.4056,-122.0775");
+        assert_eq!(details.org.as_deref(), Some("AS15169 Google LLC"));
+        assert_eq!(details.continent.as_ref().unwrap().code, "NA");
+        assert_eq!(details.carrier.unwrap().mcc, "310");
+        assert!(details.privacy.unwrap().hosting);
+        assert!(details.abuse.is_none());
+    }
+
+    #[test]
+    fn common_details_agree() {
+        let plus = plus_details();
+        let details = IpDetails::from(plus.clone());
+        let core = IpDetailsCore::from(plus.clone());
+        let lite = IpDetailsLite::from(plus.clone());
+
+        let common = IpDetailsCommon::from(&plus);
+        assert_eq!(IpDetailsCommon::from(&details), common);
+        assert_eq!(
+            IpDetailsCommon::from(&core),
+            IpDetailsCommon {
+                hostname: None,
+                ..common.clone()
+            }
+        );
+        assert_eq!(common.coordinates.unwrap().latitude(), 37.4056);
+        assert_eq!(common.continent.as_ref().unwrap().name, "North America");
+        assert_eq!(common.is_hosting, Some(true));
+
+        let common = IpDetailsCommon::from(&lite);
+        assert_eq!(common.country_code.as_deref(), Some("US"));
+        assert_eq!(common.as_name.as_deref(), Some("Google LLC"));
+        assert_eq!(common.city, None);
+        assert_eq!(common.is_hosting, None);
+    }
+}
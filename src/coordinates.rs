@@ -0,0 +1,236 @@
+//   Copyright 2019-2025 IPinfo library developers
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Geographical coordinates of IP addresses.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CoreGeo, IpDetails, IpDetailsCore, IpDetailsLite, IpDetailsPlus, IpError,
+    PlusGeo,
+};
+
+/// A validated latitude and longitude in degrees.
+///
+/// Parses from and formats as the API's `"latitude,longitude"` form.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Coordinates {
+    latitude: f64,
+    longitude: f64,
+}
+
+impl Coordinates {
+    /// Create coordinates, or `None` if the latitude is not within
+    /// [-90, 90] or the longitude is not within [-180, 180].
+    pub fn new(latitude: f64, longitude: f64) -> Option<Self> {
+        ((-90.0..=90.0).contains(&latitude)
+            && (-180.0..=180.0).contains(&longitude))
+        .then_some(Self {
+            latitude,
+            longitude,
+        })
+    }
+
+    /// The latitude in degrees, positive north of the equator.
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    /// The longitude in degrees, positive east of Greenwich.
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+
+    /// Format as a `geo:` URI (RFC 5870).
+    pub fn to_geo_uri(&self) -> String {
+        format!("geo:{self}")
+    }
+
+    /// Format in degrees, minutes and seconds, e.g.
+    /// `37°24'20.2"N 122°04'39.0"W`.
+    pub fn to_dms(&self) -> String {
+        fn dms(value: f64, positive: char, negative: char) -> String {
+            let hemisphere = if value < 0.0 { negative } else { positive };
+            let tenths = (value.abs() * 36_000.0).round() as u64;
+            let (degrees, rest) = (tenths / 36_000, tenths % 36_000);
+            let (minutes, tenths) = (rest / 600, rest % 600);
+            format!(
+                "{degrees}°{minutes:02}'{:02}.{}\"{hemisphere}",
+                tenths / 10,
+                tenths % 10
+            )
+        }
+
+        format!(
+            "{} {}",
+            dms(self.latitude, 'N', 'S'),
+            dms(self.longitude, 'E', 'W')
+        )
+    }
+
+    // Coordinates of a Core or Plus geo, where zeros mean unset
+    fn from_geo(latitude: f64, longitude: f64) -> Option<Self> {
+        if latitude == 0.0 && longitude == 0.0 {
+            return None;
+        }
+        Self::new(latitude, longitude)
+    }
+}
+
+impl fmt::Display for Coordinates {
+    /// Format as `latitude,longitude`, honoring the precision if given.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match f.precision() {
+            Some(precision) => write!(
+                f,
+                "{:.precision$},{:.precision$}",
+                self.latitude, self.longitude
+            ),
+            None => write!(f, "{},{}", self.latitude, self.longitude),
+        }
+    }
+}
+
+impl FromStr for Coordinates {
+    type Err = IpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            || err!(ParseError, &format!("invalid coordinates: {s}"));
+        let (latitude, longitude) = s.split_once(',').ok_or_else(invalid)?;
+        let latitude = latitude.trim().parse().map_err(|_| invalid())?;
+        let longitude = longitude.trim().parse().map_err(|_| invalid())?;
+        Self::new(latitude, longitude).ok_or_else(invalid)
+    }
+}
+
+impl From<Coordinates> for String {
+    fn from(coordinates: Coordinates) -> Self {
+        coordinates.to_string()
+    }
+}
+
+impl TryFrom<String> for Coordinates {
+    type Error = IpError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl IpDetails {
+    /// The coordinates parsed from `loc`, if set and valid.
+    pub fn coordinates(&self) -> Option<Coordinates> {
+        self.loc.parse().ok()
+    }
+}
+
+impl IpDetailsLite {
+    /// Always `None`, as the Lite API has no location.
+    pub fn coordinates(&self) -> Option<Coordinates> {
+        None
+    }
+}
+
+impl CoreGeo {
+    /// The coordinates, if set and valid.
+    pub fn coordinates(&self) -> Option<Coordinates> {
+        Coordinates::from_geo(self.latitude, self.longitude)
+    }
+}
+
+impl IpDetailsCore {
+    /// The coordinates of the geo details, if set and valid.
+    pub fn coordinates(&self) -> Option<Coordinates> {
+        self.geo.as_ref()?.coordinates()
+    }
+}
+
+impl PlusGeo {
+    /// The coordinates, if set and valid.
+    pub fn coordinates(&self) -> Option<Coordinates> {
+        Coordinates::from_geo(self.latitude, self.longitude)
+    }
+}
+
+impl IpDetailsPlus {
+    /// The coordinates of the geo details, if set and valid.
+    pub fn coordinates(&self) -> Option<Coordinates> {
+        self.geo.as_ref()?.coordinates()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IpDetailsCommon;
+
+    #[test]
+    fn parse_and_format() {
+        let coordinates: Coordinates =
+            "37.4056,-122.0775".parse().expect("should parse");
+        assert_eq!(coordinates.latitude(), 37.4056);
+        assert_eq!(coordinates.longitude(), -122.0775);
+        assert_eq!(coordinates.to_string(), "37.4056,-122.0775");
+        assert_eq!(format!("{coordinates:.2}"), "37.41,-122.08");
+        assert_eq!(coordinates.to_geo_uri(), "geo:37.4056,-122.0775");
+        assert_eq!(coordinates.to_dms(), "37°24'20.2\"N 122°04'39.0\"W");
+
+        assert_eq!(
+            serde_json::to_string(&coordinates).unwrap(),
+            "\"37.4056,-122.0775\""
+        );
+        assert!(serde_json::from_str::<Coordinates>("\"91,0\"").is_err());
+
+        for invalid in ["", "37.4056", "north,west", "-90.1,0", "0,180.5"] {
+            assert!(invalid.parse::<Coordinates>().is_err(), "{invalid}");
+        }
+        assert!(Coordinates::new(f64::NAN, 0.0).is_none());
+    }
+
+    #[test]
+    fn details_coordinates() {
+        let details = IpDetails {
+            loc: "-27.4820,153.0136".to_string(),
+            ..Default::default()
+        };
+        let coordinates = details.coordinates().expect("should parse");
+        assert_eq!(coordinates.latitude(), -27.482);
+        assert!(IpDetails::default().coordinates().is_none());
+
+        let core = IpDetailsCore {
+            geo: Some(CoreGeo {
+                latitude: -27.482,
+                longitude: 153.0136,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(core.coordinates(), Some(coordinates));
+        assert_eq!(
+            IpDetailsCommon::from(&core).coordinates,
+            Some(coordinates)
+        );
+        assert_eq!(IpDetails::from(core).loc, "-27.482,153.0136");
+
+        let plus = IpDetailsPlus {
+            geo: Some(PlusGeo::default()),
+            ..Default::default()
+        };
+        assert!(plus.coordinates().is_none());
+    }
+}
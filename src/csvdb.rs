@@ -0,0 +1,411 @@
+//   Copyright 2019-2025 IPinfo library developers
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Offline lookups in IPinfo CSV databases.
+//!
+//! `IpInfoLiteCsv` and `IpInfoCoreCsv` load an IPinfo CSV database into a
+//! sorted in-memory range index and look IPs up with a binary search. Rows
+//! are keyed either by `start_ip` and `end_ip` columns or by a `network`
+//! column in CIDR notation, and may mix IPv4 and IPv6. Ranges must not
+//! overlap; databases with nested or overlapping ranges fail to load.
+//!
+//! Requires the `csv` feature.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use ipinfo::IpInfoLiteCsv;
+//!
+//! let db = IpInfoLiteCsv::open("ipinfo_lite.csv").expect("should load");
+//! let details = db.lookup("8.8.8.8").expect("should lookup");
+//! println!("{}: {}", details.ip, details.country_name);
+//! ```
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    net::{IpAddr, Ipv6Addr},
+    path::Path,
+};
+
+use csv::StringRecord;
+use ipnetwork::IpNetwork;
+
+use crate::{
+    enrich::CountryData,
+    is_bogon,
+    local::{core_details, lite_details, Record},
+    IpDetailsCore, IpDetailsLite, IpError, IpInfoCoreConfig, IpInfoLiteConfig,
+};
+
+/// A row of a CSV database.
+struct CsvRecord<'a> {
+    columns: &'a HashMap<String, usize>,
+    row: &'a StringRecord,
+}
+
+impl Record for CsvRecord<'_> {
+    fn string(&self, key: &str) -> Option<String> {
+        let value = self.row.get(*self.columns.get(key)?)?;
+        (!value.is_empty()).then(|| value.to_string())
+    }
+
+    fn float(&self, key: &str) -> Option<f64> {
+        self.row.get(*self.columns.get(key)?)?.parse().ok()
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        let value = self
+            .columns
+            .get(key)
+            .and_then(|i| self.row.get(*i))
+            .unwrap_or_default();
+        value.eq_ignore_ascii_case("true") || value == "1"
+    }
+}
+
+/// An IP range and its row.
+struct Range {
+    start: u128,
+    end: u128,
+    row: StringRecord,
+}
+
+// Map IPv4 addresses into the IPv6 space so both share one index
+fn range_key(addr: IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(v4) => u128::from(v4.to_ipv6_mapped()),
+        IpAddr::V6(v6) => u128::from(v6),
+    }
+}
+
+// The address of an index key
+fn key_addr(key: u128) -> IpAddr {
+    IpAddr::V6(Ipv6Addr::from(key)).to_canonical()
+}
+
+/// An IP range index over a CSV database.
+struct RangeIndex {
+    columns: HashMap<String, usize>,
+    ranges: Vec<Range>,
+}
+
+impl RangeIndex {
+    fn from_reader(reader: impl Read) -> Result<Self, IpError> {
+        let mut reader = csv::Reader::from_reader(reader);
+        let columns: HashMap<String, usize> = reader
+            .headers()?
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.trim().to_ascii_lowercase(), i))
+            .collect();
+
+        let parse_ip = |row: &StringRecord, column: usize| {
+            let value = row.get(column).unwrap_or_default();
+            value.parse::<IpAddr>().map_err(|_| {
+                err!(DatabaseError, &format!("invalid IP address {value}"))
+            })
+        };
+
+        let mut ranges = Vec::new();
+        for row in reader.records() {
+            let row = row?;
+            let (start, end) = match (
+                columns.get("start_ip"),
+                columns.get("end_ip"),
+                columns.get("network"),
+            ) {
+                (Some(&start), Some(&end), _) => {
+                    (parse_ip(&row, start)?, parse_ip(&row, end)?)
+                }
+                (_, _, Some(&network)) => {
+                    let value = row.get(network).unwrap_or_default();
+                    let network: IpNetwork = value.parse().map_err(|_| {
+                        err!(
+                            DatabaseError,
+                            &format!("invalid network {value}")
+                        )
+                    })?;
+                    (network.network(), network.broadcast())
+                }
+                _ => {
+                    return Err(err!(
+                        DatabaseError,
+                        "missing start_ip and end_ip or network columns"
+                    ))
+                }
+            };
+            if range_key(start) > range_key(end) {
+                return Err(err!(
+                    DatabaseError,
+                    &format!("range {start} - {end} ends before it starts")
+                ));
+            }
+            ranges.push(Range {
+                start: range_key(start),
+                end: range_key(end),
+                row,
+            });
+        }
+        ranges.sort_unstable_by_key(|range| range.start);
+
+        // Lookups only check the last range starting before an address,
+        // which requires ranges not to overlap
+        if let Some(pair) = ranges.windows(2).find(|p| p[1].start <= p[0].end)
+        {
+            return Err(err!(
+                DatabaseError,
+                &format!(
+                    "overlapping ranges starting at {} and {}",
+                    key_addr(pair[0].start),
+                    key_addr(pair[1].start)
+                )
+            ));
+        }
+
+        Ok(Self { columns, ranges })
+    }
+
+    fn record(&self, ip: &str) -> Result<CsvRecord<'_>, IpError> {
+        let addr: IpAddr = ip.parse().map_err(|_| {
+            err!(ParseError, &format!("invalid IP address {ip}"))
+        })?;
+        let key = range_key(addr);
+
+        // The last range starting at or before the address
+        let i = self.ranges.partition_point(|range| range.start <= key);
+        match i.checked_sub(1).map(|i| &self.ranges[i]) {
+            Some(range) if key <= range.end => Ok(CsvRecord {
+                columns: &self.columns,
+                row: &range.row,
+            }),
+            _ => Err(err!(
+                IpRequestError,
+                &format!("{ip} not found in database")
+            )),
+        }
+    }
+}
+
+/// Offline IPinfo Lite CSV database.
+pub struct IpInfoLiteCsv {
+    index: RangeIndex,
+    country_data: CountryData,
+}
+
+impl IpInfoLiteCsv {
+    /// Load an IPinfo Lite CSV file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, IpError> {
+        let file = File::open(path)
+            .map_err(|e| err!(DatabaseError, &e.to_string()))?;
+        Self::from_reader(file)
+    }
+
+    /// Load an IPinfo Lite CSV database from a reader.
+    pub fn from_reader(reader: impl Read) -> Result<Self, IpError> {
+        Ok(Self {
+            index: RangeIndex::from_reader(reader)?,
+            country_data: CountryData::default(),
+        })
+    }
+
+    /// The number of IP ranges in the database.
+    pub fn len(&self) -> usize {
+        self.index.ranges.len()
+    }
+
+    /// Whether the database has no IP ranges.
+    pub fn is_empty(&self) -> bool {
+        self.index.ranges.is_empty()
+    }
+
+    /// Enrich details with the `default_*` country data of a client
+    /// configuration instead of the built-in tables.
+    pub fn with_country_data(mut self, config: &IpInfoLiteConfig) -> Self {
+        self.country_data = CountryData::from(config);
+        self
+    }
+
+    /// looks up IpDetailsLite for a single IP Address
+    pub fn lookup(&self, ip: &str) -> Result<IpDetailsLite, IpError> {
+        if is_bogon(ip) {
+            return Ok(IpDetailsLite {
+                ip: ip.to_string(),
+                bogon: Some(true),
+                ..Default::default()
+            });
+        }
+
+        let record = self.index.record(ip)?;
+        Ok(lite_details(ip, &record, &self.country_data))
+    }
+}
+
+/// Offline IPinfo Core CSV database.
+pub struct IpInfoCoreCsv {
+    index: RangeIndex,
+    country_data: CountryData,
+}
+
+impl IpInfoCoreCsv {
+    /// Load an IPinfo Core CSV file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, IpError> {
+        let file = File::open(path)
+            .map_err(|e| err!(DatabaseError, &e.to_string()))?;
+        Self::from_reader(file)
+    }
+
+    /// Load an IPinfo Core CSV database from a reader.
+    pub fn from_reader(reader: impl Read) -> Result<Self, IpError> {
+        Ok(Self {
+            index: RangeIndex::from_reader(reader)?,
+            country_data: CountryData::default(),
+        })
+    }
+
+    /// The number of IP ranges in the database.
+    pub fn len(&self) -> usize {
+        self.index.ranges.len()
+    }
+
+    /// Whether the database has no IP ranges.
+    pub fn is_empty(&self) -> bool {
+        self.index.ranges.is_empty()
+    }
+
+    /// Enrich details with the `default_*` country data of a client
+    /// configuration instead of the built-in tables.
+    pub fn with_country_data(mut self, config: &IpInfoCoreConfig) -> Self {
+        self.country_data = CountryData::from(config);
+        self
+    }
+
+    /// looks up IpDetailsCore for a single IP Address
+    pub fn lookup(&self, ip: &str) -> Result<IpDetailsCore, IpError> {
+        if is_bogon(ip) {
+            return Ok(IpDetailsCore {
+                ip: ip.to_string(),
+                bogon: Some(true),
+                ..Default::default()
+            });
+        }
+
+        let record = self.index.record(ip)?;
+        Ok(core_details(ip, &record, &self.country_data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IpErrorKind, LocalDatabase};
+
+    const LITE_CSV: &str = "\
+start_ip,end_ip,country,country_code,continent,continent_code,asn,as_name,as_domain
+8.8.8.0,8.8.8.255,United States,US,North America,NA,AS15169,Google LLC,google.com
+1.0.0.0,1.0.0.255,Australia,AU,Oceania,OC,AS13335,\"Cloudflare, Inc.\",cloudflare.com
+2001:4860::,2001:4860:ffff:ffff:ffff:ffff:ffff:ffff,Germany,DE,Europe,EU,AS15169,Google LLC,google.com
+";
+
+    #[test]
+    fn lookup_lite_csv() {
+        let db = IpInfoLiteCsv::from_reader(LITE_CSV.as_bytes())
+            .expect("should load");
+        assert_eq!(db.len(), 3);
+
+        let details = db.lookup("8.8.8.8").expect("should lookup");
+        assert_eq!(details.country_name, "United States");
+        assert_eq!(details.asn, "AS15169");
+        assert_eq!(details.continent.code, "NA");
+
+        let details = db.lookup("1.0.0.1").expect("should lookup");
+        assert_eq!(details.as_name, "Cloudflare, Inc.");
+
+        let details = db.lookup("2001:4860::8888").expect("should lookup");
+        assert_eq!(details.country_code, "DE");
+        assert!(details.is_eu);
+
+        assert_eq!(db.lookup("10.0.0.1").unwrap().bogon, Some(true));
+
+        let details = db
+            .lookup_details("8.8.8.8")
+            .expect("should lookup")
+            .expect("should be covered");
+        assert_eq!(details.country, "US");
+        assert_eq!(details.org.as_deref(), Some("AS15169 Google LLC"));
+        assert!(db.lookup_details("1.0.1.0").unwrap().is_none());
+        for ip in ["8.8.9.0", "1.0.1.0", "2001:4861::"] {
+            assert_eq!(
+                db.lookup(ip).err().unwrap().kind(),
+                IpErrorKind::IpRequestError
+            );
+        }
+    }
+
+    #[test]
+    fn reject_overlapping_ranges() {
+        // A /24 nested in a /16
+        let csv = "\
+network,country_code,country
+8.8.0.0/16,US,United States
+8.8.8.0/24,DE,Germany
+";
+        let err = IpInfoLiteCsv::from_reader(csv.as_bytes()).err().unwrap();
+        assert_eq!(err.kind(), IpErrorKind::DatabaseError);
+        assert_eq!(
+            err.to_string(),
+            "database error: overlapping ranges starting at 8.8.0.0 and 8.8.8.0"
+        );
+
+        let csv = "\
+start_ip,end_ip,country_code
+1.0.0.0,1.0.0.200,AU
+1.0.0.100,1.0.1.255,AU
+";
+        let err = IpInfoLiteCsv::from_reader(csv.as_bytes()).err().unwrap();
+        assert_eq!(err.kind(), IpErrorKind::DatabaseError);
+
+        let csv = "start_ip,end_ip\n1.0.0.9,1.0.0.1\n";
+        let err = IpInfoLiteCsv::from_reader(csv.as_bytes()).err().unwrap();
+        assert_eq!(err.kind(), IpErrorKind::DatabaseError);
+    }
+
+    #[test]
+    fn lookup_core_csv() {
+        let csv = "\
+network,city,region,country_code,latitude,longitude,asn,as_name,as_type,is_hosting
+8.8.8.0/24,Mountain View,California,US,37.4056,-122.0775,AS15169,Google LLC,hosting,true
+";
+        let db =
+            IpInfoCoreCsv::from_reader(csv.as_bytes()).expect("should load");
+
+        let details = db.lookup("8.8.8.8").expect("should lookup");
+        let geo = details.geo.expect("should have geo");
+        assert_eq!(geo.city.as_deref(), Some("Mountain View"));
+        assert_eq!(geo.latitude, 37.4056);
+        assert_eq!(geo.country_name.as_deref(), Some("United States"));
+        assert_eq!(details.asn.unwrap().as_type, "hosting");
+        assert!(details.is_hosting);
+        assert!(!details.is_anycast);
+
+        assert_eq!(
+            IpInfoCoreCsv::from_reader("ip,city\n8.8.8.8,x\n".as_bytes())
+                .err()
+                .unwrap()
+                .kind(),
+            IpErrorKind::DatabaseError
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//   Copyright 2019-2025 IPinfo library developers
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Static country details added to lookup results.
+
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    Continent, CountryCurrency, CountryFlag, IpDetails, IpDetailsCore,
+    IpDetailsLite, IpDetailsPlus, IpError, IpInfoConfig, IpInfoCoreConfig,
+    IpInfoLiteConfig, IpInfoPlusConfig, CONTINENTS, COUNTRIES, CURRENCIES, EU,
+    FLAGS,
+};
+
+const COUNTRY_FLAG_URL: &str =
+    "https://cdn.ipinfo.io/static/images/countries-flags/";
+
+/// Country names, EU membership, flags, currencies and continents by
+/// country code.
+#[derive(Clone)]
+pub(crate) struct CountryData {
+    countries: HashMap<String, String>,
+    eu: Vec<String>,
+    country_flags: HashMap<String, CountryFlag>,
+    country_currencies: HashMap<String, CountryCurrency>,
+    continents: HashMap<String, Continent>,
+}
+
+impl Default for CountryData {
+    fn default() -> Self {
+        Self {
+            countries: COUNTRIES.clone(),
+            eu: EU.clone(),
+            country_flags: FLAGS.clone(),
+            country_currencies: CURRENCIES.clone(),
+            continents: CONTINENTS.clone(),
+        }
+    }
+}
+
+impl CountryData {
+    /// Add the country details of the country code in the details.
+    pub(crate) fn enrich<D: Enrich>(&self, details: &mut D) {
+        details.enrich(self);
+    }
+
+    /// Parse a details response and add the country details.
+    pub(crate) fn parse<D>(&self, raw_resp: &str) -> Result<D, IpError>
+    where
+        D: DeserializeOwned + Enrich,
+    {
+        // Parse the response
+        let resp: serde_json::Value = serde_json::from_str(raw_resp)?;
+
+        // Return if an error occurred
+        if let Some(e) = resp["error"].as_str() {
+            return Err(err!(IpRequestError, e));
+        }
+
+        // Parse the results and add additional country details
+        let mut details: D = serde_json::from_str(raw_resp)?;
+        self.enrich(&mut details);
+        Ok(details)
+    }
+
+    // The flag image URL of a country
+    fn flag_url(country_code: &str) -> String {
+        let file_ext = ".svg";
+        COUNTRY_FLAG_URL.to_string() + country_code + file_ext
+    }
+}
+
+/// Implement the conversion from the `default_*` maps of a client
+/// configuration, which fall back to the built-in tables
+macro_rules! from_config {
+    ($config:ident) => {
+        impl From<&$config> for CountryData {
+            fn from(config: &$config) -> Self {
+                let default = CountryData::default();
+                #[allow(deprecated)]
+                let countries = config
+                    .default_countries
+                    .as_ref()
+                    .or(config.defaut_countries.as_ref());
+                Self {
+                    countries: countries.cloned().unwrap_or(default.countries),
+                    eu: config.default_eu.clone().unwrap_or(default.eu),
+                    country_flags: config
+                        .default_flags
+                        .clone()
+                        .unwrap_or(default.country_flags),
+                    country_currencies: config
+                        .default_currencies
+                        .clone()
+                        .unwrap_or(default.country_currencies),
+                    continents: config
+                        .default_continents
+                        .clone()
+                        .unwrap_or(default.continents),
+                }
+            }
+        }
+    };
+}
+
+from_config!(IpInfoConfig);
+from_config!(IpInfoLiteConfig);
+from_config!(IpInfoCoreConfig);
+from_config!(IpInfoPlusConfig);
+
+/// Lookup details which country details can be added to.
+pub(crate) trait Enrich {
+    /// Add the country details of the country code in the details.
+    fn enrich(&mut self, data: &CountryData);
+}
+
+impl Enrich for IpDetails {
+    fn enrich(&mut self, data: &CountryData) {
+        if !self.country.is_empty() {
+            let country = &self.country;
+            self.country_name = data.countries.get(country).cloned();
+            self.is_eu = Some(data.eu.contains(country));
+            self.country_flag = data.country_flags.get(country).cloned();
+            self.country_flag_url = Some(CountryData::flag_url(country));
+            self.country_currency =
+                data.country_currencies.get(country).cloned();
+            self.continent = data.continents.get(country).cloned();
+        }
+    }
+}
+
+impl Enrich for IpDetailsLite {
+    fn enrich(&mut self, data: &CountryData) {
+        if !self.country_code.is_empty() {
+            let country_code = &self.country_code;
+            // The response already has the name, used for unknown codes
+            if let Some(country_name) = data.countries.get(country_code) {
+                self.country_name = country_name.to_owned();
+            } else if self.country_name.is_empty() {
+                self.country_name = self.country.clone();
+            }
+            self.is_eu = data.eu.contains(country_code);
+            self.country_flag = data
+                .country_flags
+                .get(country_code)
+                .cloned()
+                .unwrap_or_default();
+            self.country_flag_url = CountryData::flag_url(country_code);
+            self.country_currency = data
+                .country_currencies
+                .get(country_code)
+                .cloned()
+                .unwrap_or_default();
+            self.continent = data
+                .continents
+                .get(country_code)
+                .cloned()
+                .unwrap_or_default();
+        }
+    }
+}
+
+impl Enrich for IpDetailsCore {
+    fn enrich(&mut self, data: &CountryData) {
+        if let Some(ref mut geo) = self.geo {
+            if let Some(ref country_code) = geo.country_code {
+                if !country_code.is_empty() {
+                    if let Some(country_name) =
+                        data.countries.get(country_code)
+                    {
+                        geo.country_name = Some(country_name.to_owned());
+                    }
+                    geo.is_eu = Some(data.eu.contains(country_code));
+                    if let Some(country_flag) =
+                        data.country_flags.get(country_code)
+                    {
+                        geo.country_flag = Some(country_flag.to_owned());
+                    }
+                    geo.country_flag_url =
+                        Some(CountryData::flag_url(country_code));
+                    if let Some(country_currency) =
+                        data.country_currencies.get(country_code)
+                    {
+                        geo.country_currency =
+                            Some(country_currency.to_owned());
+                    }
+                    if let Some(continent) = data.continents.get(country_code)
+                    {
+                        geo.continent_info = Some(continent.to_owned());
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Enrich for IpDetailsPlus {
+    fn enrich(&mut self, data: &CountryData) {
+        // Enrich geo data
+        if let Some(ref mut geo) = self.geo {
+            if let Some(ref country_code) = geo.country_code {
+                if !country_code.is_empty() {
+                    if let Some(country_name) =
+                        data.countries.get(country_code)
+                    {
+                        geo.country_name = Some(country_name.to_owned());
+                    }
+                    geo.is_eu = Some(data.eu.contains(country_code));
+                    if let Some(country_flag) =
+                        data.country_flags.get(country_code)
+                    {
+                        geo.country_flag = Some(country_flag.to_owned());
+                    }
+                    geo.country_flag_url =
+                        Some(CountryData::flag_url(country_code));
+                    if let Some(country_currency) =
+                        data.country_currencies.get(country_code)
+                    {
+                        geo.country_currency =
+                            Some(country_currency.to_owned());
+                    }
+                    if let Some(continent) = data.continents.get(country_code)
+                    {
+                        geo.continent_info = Some(continent.to_owned());
+                    }
+                }
+            }
+        }
+
+        // Enrich abuse data with country_name
+        if let Some(ref mut abuse) = self.abuse {
+            if let Some(ref country) = abuse.country {
+                if !country.is_empty() {
+                    if let Some(country_name) = data.countries.get(country) {
+                        abuse.country_name = Some(country_name.to_owned());
+                    }
+                }
+            }
+        }
+    }
+}
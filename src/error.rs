@@ -56,6 +56,12 @@ pub enum IpErrorKind {
     /// Map limit error.
     MapLimitError,
 
+    /// Invalid configuration error.
+    ConfigError,
+
+    /// Local database error.
+    DatabaseError,
+
     // Timeout exceeded.
     TimeOutError,
 }
@@ -69,6 +75,8 @@ impl IpErrorKind {
             IpErrorKind::IpRequestError => "application error",
             IpErrorKind::ParseError => "parse error",
             IpErrorKind::TimeOutError => "timeout has been exceeded",
+            IpErrorKind::ConfigError => "invalid configuration",
+            IpErrorKind::DatabaseError => "database error",
             IpErrorKind::MapLimitError => {
                 "You have exceeded maximum IP upload limit per request."
             }
@@ -83,10 +91,11 @@ impl fmt::Display for IpErrorKind {
 }
 
 /// The IpError type is the only error type that can be returned from this crate's API.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct IpError {
     kind: IpErrorKind,
     description: Option<String>,
+    status: Option<u16>,
 }
 
 impl IpError {
@@ -103,6 +112,7 @@ impl IpError {
         Self {
             kind,
             description: description.map(|desc| desc.to_string()),
+            status: None,
         }
     }
 
@@ -119,6 +129,19 @@ impl IpError {
     pub fn kind(&self) -> IpErrorKind {
         self.kind
     }
+
+    /// Get the HTTP status of the response that caused this error, if any.
+    pub fn status(&self) -> Option<u16> {
+        self.status
+    }
+
+    /// Whether the error is a property of the request rather than of the
+    /// service, so repeating the request fails the same way: an API error
+    /// response, or an invalid address or resource.
+    pub(crate) fn is_deterministic(&self) -> bool {
+        self.kind == IpErrorKind::IpRequestError
+            || matches!(self.status, Some(400 | 404))
+    }
 }
 
 impl fmt::Display for IpError {
@@ -137,6 +160,7 @@ impl From<IpErrorKind> for IpError {
         Self {
             kind,
             description: None,
+            status: None,
         }
     }
 }
@@ -144,10 +168,13 @@ impl From<IpErrorKind> for IpError {
 impl From<reqwest::Error> for IpError {
     fn from(err: reqwest::Error) -> Self {
         match err.status() {
-            Some(status) => err!(
-                HTTPClientError,
-                &format!("{}: {}", status, &err.to_string())
-            ),
+            Some(status) => IpError {
+                status: Some(status.as_u16()),
+                ..err!(
+                    HTTPClientError,
+                    &format!("{}: {}", status, &err.to_string())
+                )
+            },
             None => err!(HTTPClientError, &err.to_string()),
         }
     }
@@ -159,6 +186,20 @@ impl From<serde_json::Error> for IpError {
     }
 }
 
+#[cfg(feature = "mmdb")]
+impl From<maxminddb::MaxMindDBError> for IpError {
+    fn from(err: maxminddb::MaxMindDBError) -> Self {
+        err!(DatabaseError, &err.to_string())
+    }
+}
+
+#[cfg(feature = "csv")]
+impl From<csv::Error> for IpError {
+    fn from(err: csv::Error) -> Self {
+        err!(DatabaseError, &err.to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,425 @@
+//   Copyright 2019-2025 IPinfo library developers
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Hybrid local-then-remote lookups.
+//!
+//! `IpInfoHybrid` answers from a local database first and only calls the API
+//! when the local data lacks some of the requested fields or does not cover
+//! the address. The API details then fill in the missing fields, and every
+//! field records where it came from.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # #[cfg(feature = "mmdb")]
+//! # async fn run() -> Result<(), ipinfo::IpError> {
+//! use ipinfo::{DetailField, IpInfo, IpInfoCoreDb, IpInfoHybrid, Source};
+//!
+//! let local = IpInfoCoreDb::open("ipinfo_core.mmdb")?;
+//! let remote = IpInfo::new(Default::default())?;
+//! let mut ipinfo = IpInfoHybrid::new(local, remote);
+//!
+//! let res = ipinfo
+//!     .lookup("8.8.8.8", &[DetailField::City, DetailField::Privacy])
+//!     .await?;
+//! assert_eq!(res.sources[&DetailField::City], Source::Local);
+//! assert_eq!(res.sources[&DetailField::Privacy], Source::Remote);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{IpDetails, IpError, IpInfo};
+
+/// A local database that can answer lookups without calling the API.
+pub trait LocalDatabase: Send + Sync {
+    /// Look up the details of an IP address, or `None` if the database does
+    /// not cover it.
+    fn lookup_details(&self, ip: &str) -> Result<Option<IpDetails>, IpError>;
+}
+
+impl<D: LocalDatabase + ?Sized> LocalDatabase for Arc<D> {
+    fn lookup_details(&self, ip: &str) -> Result<Option<IpDetails>, IpError> {
+        (**self).lookup_details(ip)
+    }
+}
+
+/// A group of fields of `IpDetails`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum DetailField {
+    /// The `hostname` field.
+    Hostname,
+
+    /// The `city` field.
+    City,
+
+    /// The `region` field.
+    Region,
+
+    /// The `country` field and its enrichment.
+    Country,
+
+    /// The `loc` field.
+    Loc,
+
+    /// The `org` field.
+    Org,
+
+    /// The `postal` field.
+    Postal,
+
+    /// The `timezone` field.
+    Timezone,
+
+    /// The `asn` details.
+    Asn,
+
+    /// The `company` details.
+    Company,
+
+    /// The `carrier` details.
+    Carrier,
+
+    /// The `privacy` details.
+    Privacy,
+
+    /// The `abuse` details.
+    Abuse,
+
+    /// The `domains` details.
+    Domains,
+}
+
+impl DetailField {
+    /// All fields.
+    pub const ALL: [DetailField; 14] = [
+        DetailField::Hostname,
+        DetailField::City,
+        DetailField::Region,
+        DetailField::Country,
+        DetailField::Loc,
+        DetailField::Org,
+        DetailField::Postal,
+        DetailField::Timezone,
+        DetailField::Asn,
+        DetailField::Company,
+        DetailField::Carrier,
+        DetailField::Privacy,
+        DetailField::Abuse,
+        DetailField::Domains,
+    ];
+
+    // Whether the field is set in the details
+    fn is_set(self, details: &IpDetails) -> bool {
+        match self {
+            DetailField::Hostname => details.hostname.is_some(),
+            DetailField::City => !details.city.is_empty(),
+            DetailField::Region => !details.region.is_empty(),
+            DetailField::Country => !details.country.is_empty(),
+            DetailField::Loc => !details.loc.is_empty(),
+            DetailField::Org => details.org.is_some(),
+            DetailField::Postal => details.postal.is_some(),
+            DetailField::Timezone => details.timezone.is_some(),
+            DetailField::Asn => details.asn.is_some(),
+            DetailField::Company => details.company.is_some(),
+            DetailField::Carrier => details.carrier.is_some(),
+            DetailField::Privacy => details.privacy.is_some(),
+            DetailField::Abuse => details.abuse.is_some(),
+            DetailField::Domains => details.domains.is_some(),
+        }
+    }
+
+    // Copy the field from one set of details to another
+    fn copy(self, from: &IpDetails, to: &mut IpDetails) {
+        match self {
+            DetailField::Hostname => to.hostname.clone_from(&from.hostname),
+            DetailField::City => to.city.clone_from(&from.city),
+            DetailField::Region => to.region.clone_from(&from.region),
+            DetailField::Country => {
+                to.country.clone_from(&from.country);
+                to.country_name.clone_from(&from.country_name);
+                to.is_eu = from.is_eu;
+                to.country_flag.clone_from(&from.country_flag);
+                to.country_flag_url.clone_from(&from.country_flag_url);
+                to.country_currency.clone_from(&from.country_currency);
+                to.continent.clone_from(&from.continent);
+            }
+            DetailField::Loc => to.loc.clone_from(&from.loc),
+            DetailField::Org => to.org.clone_from(&from.org),
+            DetailField::Postal => to.postal.clone_from(&from.postal),
+            DetailField::Timezone => to.timezone.clone_from(&from.timezone),
+            DetailField::Asn => to.asn.clone_from(&from.asn),
+            DetailField::Company => to.company.clone_from(&from.company),
+            DetailField::Carrier => to.carrier.clone_from(&from.carrier),
+            DetailField::Privacy => to.privacy.clone_from(&from.privacy),
+            DetailField::Abuse => to.abuse.clone_from(&from.abuse),
+            DetailField::Domains => to.domains.clone_from(&from.domains),
+        }
+    }
+}
+
+/// Where a field of hybrid lookup details came from.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Source {
+    /// The local database.
+    Local,
+
+    /// The IPinfo API.
+    Remote,
+}
+
+/// Details of a hybrid lookup.
+#[derive(Debug, Clone)]
+pub struct HybridDetails {
+    /// The merged details.
+    pub details: IpDetails,
+
+    /// The source of every field set in the details.
+    pub sources: HashMap<DetailField, Source>,
+}
+
+/// Hybrid local-then-remote lookup context structure.
+pub struct IpInfoHybrid {
+    local: Box<dyn LocalDatabase>,
+    remote: IpInfo,
+}
+
+impl IpInfoHybrid {
+    /// Construct a new IpInfoHybrid structure from a local database and the
+    /// client used for the API.
+    pub fn new(local: impl LocalDatabase + 'static, remote: IpInfo) -> Self {
+        Self {
+            local: Box::new(local),
+            remote,
+        }
+    }
+
+    /// Looks up the given fields for a single IP address, calling the API
+    /// only if the local database lacks some of them.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), err)
+    )]
+    pub async fn lookup(
+        &mut self,
+        ip: &str,
+        fields: &[DetailField],
+    ) -> Result<HybridDetails, IpError> {
+        // Enrich local details with the country data of the client
+        let local = self.local.lookup_details(ip)?.map(|mut details| {
+            self.remote.country_data().enrich(&mut details);
+            details
+        });
+        if let Some(details) = &local {
+            let complete = fields.iter().all(|field| field.is_set(details));
+            if complete || details.bogon == Some(true) {
+                event!(debug, "answered from local database");
+                return Ok(Self::merge(local, None));
+            }
+        }
+
+        event!(debug, "looking up missing fields remotely");
+        let remote = self.remote.lookup(ip).await?;
+        Ok(Self::merge(local, Some(remote)))
+    }
+
+    // Fill the fields missing from the local details with the remote ones
+    fn merge(
+        local: Option<IpDetails>,
+        remote: Option<IpDetails>,
+    ) -> HybridDetails {
+        let mut sources = HashMap::new();
+        let mut details = match (local, &remote) {
+            (Some(local), _) => local,
+            (None, Some(remote)) => IpDetails {
+                ip: remote.ip.clone(),
+                bogon: remote.bogon,
+                ..Default::default()
+            },
+            (None, None) => IpDetails::default(),
+        };
+
+        for field in DetailField::ALL {
+            if field.is_set(&details) {
+                sources.insert(field, Source::Local);
+            } else if let Some(remote) = &remote {
+                if field.is_set(remote) {
+                    field.copy(remote, &mut details);
+                    sources.insert(field, Source::Remote);
+                }
+            }
+        }
+
+        HybridDetails { details, sources }
+    }
+}
+
+/// Implement `LocalDatabase` for an offline database
+#[cfg(any(feature = "mmdb", feature = "csv"))]
+macro_rules! local_database {
+    ($db:ty) => {
+        impl LocalDatabase for $db {
+            fn lookup_details(
+                &self,
+                ip: &str,
+            ) -> Result<Option<IpDetails>, IpError> {
+                match self.lookup(ip) {
+                    Ok(details) => Ok(Some(details.into())),
+                    Err(e)
+                        if e.kind() == crate::IpErrorKind::IpRequestError =>
+                    {
+                        Ok(None)
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+        }
+    };
+}
+
+#[cfg(feature = "mmdb")]
+local_database!(crate::IpInfoLiteDb);
+#[cfg(feature = "mmdb")]
+local_database!(crate::IpInfoCoreDb);
+#[cfg(feature = "csv")]
+local_database!(crate::IpInfoLiteCsv);
+#[cfg(feature = "csv")]
+local_database!(crate::IpInfoCoreCsv);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AsnDetails, IpInfoConfig};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    struct StaticDatabase;
+
+    impl LocalDatabase for StaticDatabase {
+        fn lookup_details(
+            &self,
+            ip: &str,
+        ) -> Result<Option<IpDetails>, IpError> {
+            if ip != "8.8.8.8" {
+                return Ok(None);
+            }
+            Ok(Some(IpDetails {
+                ip: ip.to_string(),
+                city: "Mountain View".to_string(),
+                country: "US".to_string(),
+                loc: "37.4056,-122.0775".to_string(),
+                org: Some("AS15169 Google LLC".to_string()),
+                asn: Some(AsnDetails {
+                    asn: "AS15169".to_string(),
+                    name: "Google LLC".to_string(),
+                    domain: "google.com".to_string(),
+                    route: String::new(),
+                    asn_type: String::new(),
+                }),
+                ..Default::default()
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn lookup_hybrid() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/8.8.8.8"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "ip": "8.8.8.8",
+                    "city": "Remote City",
+                    "region": "California",
+                    "country": "US",
+                    "loc": "37.4056,-122.0775",
+                    "privacy": {
+                        "vpn": false,
+                        "proxy": false,
+                        "tor": false,
+                        "relay": false,
+                        "hosting": true,
+                        "service": ""
+                    }
+                }),
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/1.1.1.1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "ip": "1.1.1.1",
+                    "city": "Brisbane",
+                    "region": "Queensland",
+                    "country": "AU",
+                    "loc": "-27.4820,153.0136"
+                }),
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let remote = IpInfo::new(IpInfoConfig {
+            token: Some("test_token".into()),
+            base_url: Some(mock_server.uri()),
+            default_countries: Some(HashMap::from([(
+                "US".to_string(),
+                "Vereinigte Staaten".to_string(),
+            )])),
+            ..Default::default()
+        })
+        .expect("should construct");
+        let mut ipinfo = IpInfoHybrid::new(StaticDatabase, remote);
+
+        // Answered locally
+        let res = ipinfo
+            .lookup("8.8.8.8", &[DetailField::City, DetailField::Asn])
+            .await
+            .expect("should lookup");
+        assert_eq!(res.details.city, "Mountain View");
+        assert_eq!(res.details.org.as_deref(), Some("AS15169 Google LLC"));
+        assert_eq!(
+            res.details.country_name.as_deref(),
+            Some("Vereinigte Staaten")
+        );
+        assert_eq!(res.sources[&DetailField::City], Source::Local);
+        assert!(!res.sources.contains_key(&DetailField::Privacy));
+
+        // Privacy is only available remotely
+        let res = ipinfo
+            .lookup("8.8.8.8", &[DetailField::City, DetailField::Privacy])
+            .await
+            .expect("should lookup");
+        assert_eq!(res.details.city, "Mountain View");
+        assert_eq!(res.details.region, "California");
+        assert!(res.details.privacy.unwrap().hosting);
+        assert_eq!(res.sources[&DetailField::City], Source::Local);
+        assert_eq!(res.sources[&DetailField::Region], Source::Remote);
+        assert_eq!(res.sources[&DetailField::Privacy], Source::Remote);
+
+        // Not covered locally
+        let res = ipinfo
+            .lookup("1.1.1.1", &[DetailField::City])
+            .await
+            .expect("should lookup");
+        assert_eq!(res.details.ip, "1.1.1.1");
+        assert_eq!(res.details.city, "Brisbane");
+        assert_eq!(res.sources[&DetailField::City], Source::Remote);
+    }
+}
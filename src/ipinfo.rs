@@ -12,41 +12,79 @@
 //   See the License for the specific language governing permissions and
 //   limitations under the License.
 
-use std::{collections::HashMap, num::NonZeroUsize, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use crate::{
-    cache_key, is_bogon, Continent, CountryCurrency, CountryFlag, IpDetails,
-    IpError, ResproxyDetails, BATCH_MAX_SIZE, BATCH_REQ_TIMEOUT_DEFAULT,
-    CONTINENTS, COUNTRIES, CURRENCIES, EU, FLAGS, VERSION,
+    cache::{restored_entries, Cached, DetailsCache, Refreshes},
+    enrich::CountryData,
+    is_bogon,
+    metrics::record_cache,
+    middleware::send,
+    token::TokenAuth,
+    util::validate_config,
+    AccountInfo, CacheMode, CacheSnapshot, CacheTtl, Continent,
+    CountryCurrency, CountryFlag, IpDetails, IpError, IpField, IpSummary,
+    MapReport, MetricsSink, Middleware, ResproxyDetails, RetryPolicy, Token,
+    BATCH_MAX_SIZE, BATCH_REQ_TIMEOUT_DEFAULT, UPLOAD_MAX_SIZE, VERSION,
 };
 
-use lru::LruCache;
+use ipnetwork::IpNetwork;
+use serde::Deserialize;
 use serde_json::json;
 
-use reqwest::header::{
-    HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE, USER_AGENT,
+use reqwest::{
+    header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE, USER_AGENT},
+    RequestBuilder, Url,
 };
 
 use tokio::time::timeout;
 
-const COUNTRY_FLAG_URL: &str =
-    "https://cdn.ipinfo.io/static/images/countries-flags/";
-
 const BASE_URL: &str = "https://ipinfo.io";
 const BASE_URL_V6: &str = "https://v6.ipinfo.io";
 
 /// IpInfo structure configuration.
 pub struct IpInfoConfig {
     /// IPinfo access token.
-    pub token: Option<String>,
+    pub token: Option<Token>,
 
     /// The timeout of HTTP requests. (default: 3 seconds)
     pub timeout: Duration,
 
+    /// HTTP client to send all requests with, e.g. one configured with a
+    /// proxy or custom root certificates. `timeout` is not applied to a
+    /// provided client. (default: a new client)
+    pub client: Option<reqwest::Client>,
+
+    /// Middleware applied to every request, in order. (default: none)
+    pub middleware: Vec<Arc<dyn Middleware>>,
+
+    /// Sink receiving request, cache and batch metrics. (default: none)
+    pub metrics: Option<Arc<dyn MetricsSink>>,
+
+    /// Retry policy for failed requests. (default: no retries)
+    pub retry: RetryPolicy,
+
     /// The size of the LRU cache. (default: 100 IPs)
     pub cache_size: usize,
 
+    /// How lookup results are cached. (default: by IP address)
+    pub cache_mode: CacheMode,
+
+    /// How long lookup results are cached, and when they are refreshed in
+    /// the background. (default: until evicted)
+    pub cache_ttl: Option<CacheTtl>,
+
+    /// How long failed lookups that would fail again, such as an invalid
+    /// IP address, are cached and return the same error without a request.
+    /// (default: not cached)
+    pub negative_cache_ttl: Option<Duration>,
+
     // Default mapping of country codes to country names
+    pub default_countries: Option<HashMap<String, String>>,
+
+    /// Former, misspelled name of `default_countries`, used when that is
+    /// not set.
+    #[deprecated(note = "use `default_countries` instead")]
     pub defaut_countries: Option<HashMap<String, String>>,
 
     // Default list of EU countries
@@ -61,9 +99,12 @@ pub struct IpInfoConfig {
     // Default mapping of country codes to their respective continent code and name
     pub default_continents: Option<HashMap<String, Continent>>,
 
-    /// Custom base URL for API requests (used for testing). If None, uses the default URL.
-    #[doc(hidden)]
+    /// Base URL for API requests. (default: https://ipinfo.io)
     pub base_url: Option<String>,
+
+    /// Base URL for API requests over IPv6, used to look up your own v6 IP.
+    /// (default: https://v6.ipinfo.io)
+    pub base_url_v6: Option<String>,
 }
 
 impl Default for IpInfoConfig {
@@ -71,28 +112,46 @@ impl Default for IpInfoConfig {
         Self {
             token: None,
             timeout: Duration::from_secs(3),
+            client: None,
+            middleware: Vec::new(),
+            metrics: None,
+            retry: RetryPolicy::default(),
             cache_size: 100,
+            cache_mode: CacheMode::default(),
+            cache_ttl: None,
+            negative_cache_ttl: None,
+            default_countries: None,
+            #[allow(deprecated)]
             defaut_countries: None,
             default_eu: None,
             default_flags: None,
             default_currencies: None,
             default_continents: None,
             base_url: None,
+            base_url_v6: None,
         }
     }
 }
 
+/// Response of the domain ranges endpoint.
+#[derive(Deserialize)]
+struct RangesDetails {
+    ranges: Vec<IpNetwork>,
+}
+
 /// IPinfo requests context structure.
 pub struct IpInfo {
-    token: Option<String>,
+    token: Option<Token>,
     client: reqwest::Client,
-    cache: LruCache<String, IpDetails>,
-    countries: HashMap<String, String>,
-    eu: Vec<String>,
-    country_flags: HashMap<String, CountryFlag>,
-    country_currencies: HashMap<String, CountryCurrency>,
-    continents: HashMap<String, Continent>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    metrics: Option<Arc<dyn MetricsSink>>,
+    retry: RetryPolicy,
+    cache: DetailsCache<IpDetails>,
+    refreshes: Refreshes,
+    field_cache: DetailsCache<Option<String>>,
+    country_data: CountryData,
     base_url: String,
+    base_url_v6: String,
 }
 
 pub struct BatchReqOpts {
@@ -122,36 +181,51 @@ impl IpInfo {
     /// let ipinfo = IpInfo::new(Default::default()).expect("should construct");
     /// ```
     pub fn new(config: IpInfoConfig) -> Result<Self, IpError> {
-        let client =
-            reqwest::Client::builder().timeout(config.timeout).build()?;
+        let cache_size = validate_config(
+            config.cache_size,
+            config.timeout,
+            [config.base_url.as_deref(), config.base_url_v6.as_deref()],
+        )?;
+        let country_data = CountryData::from(&config);
+        let client = match config.client {
+            Some(client) => client,
+            None => {
+                reqwest::Client::builder().timeout(config.timeout).build()?
+            }
+        };
+        let refreshes = Refreshes::new(
+            &client,
+            &config.middleware,
+            &config.metrics,
+            &config.retry,
+            "lookup",
+        );
 
-        let mut ipinfo_obj = Self {
+        Ok(Self {
             client,
+            middleware: config.middleware,
+            metrics: config.metrics,
+            retry: config.retry,
             token: config.token,
-            cache: LruCache::new(
-                NonZeroUsize::new(config.cache_size).unwrap(),
+            cache: DetailsCache::new(
+                cache_size,
+                config.cache_mode,
+                config.cache_ttl,
+                config.negative_cache_ttl,
+            ),
+            refreshes,
+            field_cache: DetailsCache::new(
+                cache_size,
+                config.cache_mode,
+                config.cache_ttl,
+                config.negative_cache_ttl,
             ),
-            countries: HashMap::new(),
-            eu: Vec::new(),
-            country_flags: HashMap::new(),
-            country_currencies: HashMap::new(),
-            continents: HashMap::new(),
+            country_data,
             base_url: config.base_url.unwrap_or_else(|| BASE_URL.to_string()),
-        };
-
-        ipinfo_obj.countries =
-            config.defaut_countries.unwrap_or_else(|| COUNTRIES.clone());
-        ipinfo_obj.eu = config.default_eu.unwrap_or_else(|| EU.clone());
-        ipinfo_obj.country_flags =
-            config.default_flags.unwrap_or_else(|| FLAGS.clone());
-        ipinfo_obj.country_currencies = config
-            .default_currencies
-            .unwrap_or_else(|| CURRENCIES.clone());
-        ipinfo_obj.continents = config
-            .default_continents
-            .unwrap_or_else(|| CONTINENTS.clone());
-
-        Ok(ipinfo_obj)
+            base_url_v6: config
+                .base_url_v6
+                .unwrap_or_else(|| BASE_URL_V6.to_string()),
+        })
     }
 
     /// Lookup IPDetails for a list of one or more IP addresses.
@@ -185,6 +259,10 @@ impl IpInfo {
     }
 
     // Internal lookup_batch function. This ignores the total timeout condition
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self, ips, batch_config), fields(ips = ips.len()), err)
+    )]
     async fn _lookup_batch(
         &mut self,
         ips: &[&str],
@@ -193,10 +271,11 @@ impl IpInfo {
         let mut results: HashMap<String, IpDetails> = HashMap::new();
 
         // Collect a list of ips we need to lookup.
-        // Filters out bogons and cache hits
+        // Filters out bogons and cache hits, refetching stale results
         let mut work = vec![];
         for ip in ips.iter() {
             if is_bogon(ip) {
+                event!(debug, ip = *ip, "bogon address, skipping lookup");
                 results.insert(
                     ip.to_string(),
                     IpDetails {
@@ -205,30 +284,36 @@ impl IpInfo {
                         ..Default::default()
                     },
                 );
-            } else if let Some(detail) = self.cache.get(&cache_key(ip)) {
-                results.insert(ip.to_string(), detail.clone());
+            } else if let Some(Cached::Fresh(detail)) = self.cache.get(ip) {
+                event!(trace, ip = *ip, "cache hit");
+                record_cache(&self.metrics, true);
+                results.insert(ip.to_string(), detail);
             } else {
+                event!(trace, ip = *ip, "cache miss");
+                record_cache(&self.metrics, false);
                 work.push(*ip);
             }
         }
 
-        let client = reqwest::Client::builder()
-            .timeout(batch_config.timeout_per_batch)
-            .build()?;
-
         // Remove duplicates
         work.sort();
         work.dedup();
 
         // Make batched requests
         for batch in work.chunks(batch_config.batch_size as usize) {
-            let response = self.batch_request(client.clone(), batch).await?;
+            event!(debug, size = batch.len(), "dispatching batch");
+            if let Some(metrics) = &self.metrics {
+                metrics.record_batch(batch.len());
+            }
+            let response = self
+                .batch_request(batch, batch_config.timeout_per_batch)
+                .await?;
             results.extend(response);
         }
 
         // Add country_name and EU status to response
         for detail in results.values_mut() {
-            self.populate_static_details(detail);
+            self.country_data.enrich(detail);
         }
 
         // Update cache
@@ -236,7 +321,7 @@ impl IpInfo {
             .iter()
             .filter(|(ip, _)| !is_bogon(ip))
             .for_each(|x| {
-                self.cache.put(cache_key(x.0.as_str()), x.1.clone());
+                self.cache.put(x.0, x.1.clone());
             });
 
         Ok(results)
@@ -244,25 +329,26 @@ impl IpInfo {
 
     async fn batch_request(
         &self,
-        client: reqwest::Client,
         ips: &[&str],
+        timeout_per_batch: Duration,
     ) -> Result<HashMap<String, IpDetails>, IpError> {
         // Lookup cache misses which are not bogon
-        let response = client
-            .post(format!("{BASE_URL}/batch"))
+        let request = self
+            .client
+            .post(format!("{}/batch", self.base_url))
+            .timeout(timeout_per_batch)
             .headers(Self::construct_headers())
-            .bearer_auth(self.token.as_deref().unwrap_or_default())
-            .json(&json!(ips))
-            .send()
-            .await?;
-
-        // Check if we exhausted our request quota
-        if let reqwest::StatusCode::TOO_MANY_REQUESTS = response.status() {
-            return Err(err!(RateLimitExceededError));
-        }
-
-        // Acquire response
-        let raw_resp = response.error_for_status()?.text().await?;
+            .token_auth(self.token.as_ref())
+            .json(&json!(ips));
+        let raw_resp = send(
+            &self.client,
+            &self.middleware,
+            self.metrics.as_deref(),
+            &self.retry,
+            "batch",
+            request,
+        )
+        .await?;
 
         // Parse the response
         let resp: serde_json::Value = serde_json::from_str(&raw_resp)?;
@@ -292,7 +378,8 @@ impl IpInfo {
     /// }
     /// ```
     pub async fn lookup(&mut self, ip: &str) -> Result<IpDetails, IpError> {
-        self._lookup(ip, BASE_URL).await
+        let base_url = self.base_url.clone();
+        self._lookup(ip, &base_url).await
     }
 
     /// looks up IPDetails of your own v4 IP
@@ -309,7 +396,8 @@ impl IpInfo {
     /// }
     /// ```
     pub async fn lookup_self_v4(&mut self) -> Result<IpDetails, IpError> {
-        self._lookup("", BASE_URL).await
+        let base_url = self.base_url.clone();
+        self._lookup("", &base_url).await
     }
 
     /// looks up IPDetails of your own v6 IP
@@ -326,15 +414,45 @@ impl IpInfo {
     /// }
     /// ```
     pub async fn lookup_self_v6(&mut self) -> Result<IpDetails, IpError> {
-        self._lookup("", BASE_URL_V6).await
+        let base_url = self.base_url_v6.clone();
+        self._lookup("", &base_url).await
+    }
+
+    /// The country data lookup details are enriched with.
+    pub(crate) fn country_data(&self) -> &CountryData {
+        &self.country_data
+    }
+
+    /// Snapshot the cached lookup results, e.g. to start another client with
+    /// a warm cache.
+    pub fn export_cache(&self) -> CacheSnapshot<IpDetails> {
+        self.cache.snapshot()
+    }
+
+    /// Preload the cache from a snapshot, returning the number of results
+    /// loaded.
+    pub fn import_cache(
+        &mut self,
+        snapshot: CacheSnapshot<IpDetails>,
+    ) -> usize {
+        let mut entries = restored_entries(snapshot);
+        for entry in &mut entries {
+            self.country_data.enrich(&mut entry.details);
+        }
+        self.cache.import(entries)
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), err)
+    )]
     async fn _lookup(
         &mut self,
         ip: &str,
         base_url: &str,
     ) -> Result<IpDetails, IpError> {
         if is_bogon(ip) {
+            event!(debug, "bogon address, skipping lookup");
             return Ok(IpDetails {
                 ip: ip.to_string(),
                 bogon: Some(true),
@@ -342,29 +460,241 @@ impl IpInfo {
             });
         }
 
-        // Check for cache hit
-        let cached_detail = self.cache.get(&cache_key(ip));
+        self.apply_refreshes();
+
+        // Check for cache hit, refreshing stale results in the background
+        let cached_detail = self.cache.get(ip);
 
         if let Some(cached_detail) = cached_detail {
-            return Ok(cached_detail.clone());
+            if !cached_detail.is_stale()
+                || self.refreshes.in_flight(ip)
+                || self
+                    .refreshes
+                    .refresh(ip, self.details_request(ip, base_url))
+            {
+                event!(trace, "cache hit");
+                record_cache(&self.metrics, true);
+                return Ok(cached_detail.into_inner());
+            }
+        }
+
+        // Check for a recent failure which would repeat
+        if let Some(e) = self.cache.get_error(ip) {
+            event!(trace, "negative cache hit");
+            record_cache(&self.metrics, true);
+            return Err(e);
         }
+        event!(trace, "cache miss");
+        record_cache(&self.metrics, false);
 
         // lookup in case of a cache miss
-        let response = self
-            .client
+        let details = match self.request_details(ip, base_url).await {
+            Ok(details) => details,
+            Err(e) => {
+                self.cache.put_error(ip, &e);
+                return Err(e);
+            }
+        };
+
+        // update cache
+        self.cache.put(ip, details.clone());
+        Ok(details)
+    }
+
+    // Build the request for the details of an IP address
+    fn details_request(&self, ip: &str, base_url: &str) -> RequestBuilder {
+        self.client
             .get(format!("{base_url}/{ip}"))
             .headers(Self::construct_headers())
-            .bearer_auth(self.token.as_deref().unwrap_or_default())
-            .send()
-            .await?;
+            .token_auth(self.token.as_ref())
+    }
+
+    // Request the details of an IP address from the API
+    async fn request_details(
+        &self,
+        ip: &str,
+        base_url: &str,
+    ) -> Result<IpDetails, IpError> {
+        let request = self.details_request(ip, base_url);
+        let raw_resp = send(
+            &self.client,
+            &self.middleware,
+            self.metrics.as_deref(),
+            &self.retry,
+            "lookup",
+            request,
+        )
+        .await?;
+        self.country_data.parse(&raw_resp)
+    }
+
+    // Cache the results of completed background refreshes
+    fn apply_refreshes(&mut self) {
+        let completed =
+            self.refreshes.completed(|raw| self.country_data.parse(raw));
+        for (ip, details) in completed {
+            self.cache.put(&ip, details);
+        }
+    }
+
+    /// looks up a single field of the IPDetails for an IP Address
+    ///
+    /// The value is returned as plain text, which avoids fetching and
+    /// parsing the full details when only one field is needed. Fields
+    /// without a value, and those of bogon addresses, resolve to None.
+    ///
+    /// Values are cached like full lookups, and are also served from the
+    /// cached details of the address. Stale values are fetched again
+    /// rather than refreshed in the background.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ipinfo::{IpField, IpInfo};
+    ///
+    ///  #[tokio::main]
+    /// async fn main() {
+    ///     let mut ipinfo = IpInfo::new(Default::default()).expect("should construct");
+    ///     let country = ipinfo.lookup_field("8.8.8.8", IpField::Country).await.expect("should run");
+    /// }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), err)
+    )]
+    pub async fn lookup_field(
+        &mut self,
+        ip: &str,
+        field: IpField,
+    ) -> Result<Option<String>, IpError> {
+        if is_bogon(ip) {
+            event!(debug, "bogon address, skipping lookup");
+            return Ok(None);
+        }
+
+        // Check for cache hit, in the full details or the field results
+        let key = format!("{ip}/{field}");
+        let cached_value = self
+            .cache
+            .get(ip)
+            .filter(|cached| !cached.is_stale())
+            .and_then(|cached| field.value(&cached.into_inner()).map(Some))
+            .or_else(|| {
+                self.field_cache
+                    .get(&key)
+                    .filter(|cached| !cached.is_stale())
+                    .map(Cached::into_inner)
+            });
+        if let Some(cached_value) = cached_value {
+            event!(trace, "cache hit");
+            record_cache(&self.metrics, true);
+            return Ok(cached_value);
+        }
+
+        // Check for a recent failure which would repeat
+        if let Some(e) = self.field_cache.get_error(&key) {
+            event!(trace, "negative cache hit");
+            record_cache(&self.metrics, true);
+            return Err(e);
+        }
+        event!(trace, "cache miss");
+        record_cache(&self.metrics, false);
+
+        // lookup in case of a cache miss
+        let value = match self.request_field(ip, field).await {
+            Ok(value) => value,
+            Err(e) => {
+                self.field_cache.put_error(&key, &e);
+                return Err(e);
+            }
+        };
+
+        // update cache
+        self.field_cache.put(&key, value.clone());
+        Ok(value)
+    }
+
+    // Request a single field of the details of an IP address from the API
+    async fn request_field(
+        &self,
+        ip: &str,
+        field: IpField,
+    ) -> Result<Option<String>, IpError> {
+        // The field endpoints respond with plain text
+        let mut headers = Self::construct_headers();
+        headers.insert(ACCEPT, HeaderValue::from_static("text/plain"));
+
+        let request = self
+            .client
+            .get(format!("{}/{ip}/{field}", self.base_url))
+            .headers(headers)
+            .token_auth(self.token.as_ref());
+        let raw_resp = send(
+            &self.client,
+            &self.middleware,
+            self.metrics.as_deref(),
+            &self.retry,
+            "field",
+            request,
+        )
+        .await?;
+
+        // Return if an error occurred
+        if let Ok(resp) = serde_json::from_str::<serde_json::Value>(&raw_resp)
+        {
+            if let Some(e) = resp["error"].as_str() {
+                return Err(err!(IpRequestError, e));
+            }
+        }
 
-        // Check if we exhausted our request quota
-        if let reqwest::StatusCode::TOO_MANY_REQUESTS = response.status() {
-            return Err(err!(RateLimitExceededError));
+        // The API responds with "undefined" for fields without a value
+        match raw_resp.trim() {
+            "undefined" => Ok(None),
+            value => Ok(Some(value.to_string())),
         }
+    }
 
-        // Acquire response
-        let raw_resp = response.error_for_status()?.text().await?;
+    /// Get a mapping of a list of IPs on a world map
+    ///
+    /// Accepts any iterator of displayable IPs, such as `&str` or `IpAddr`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ipinfo::IpInfo;
+    ///
+    ///  #[tokio::main]
+    /// async fn main() {
+    ///     let ipinfo = IpInfo::new(Default::default()).expect("should construct");
+    ///     let report = ipinfo.get_map(&["8.8.8.8", "4.2.2.4"]).await.expect("should run");
+    ///     println!("{}", report.report_url);
+    /// }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self, ips), err)
+    )]
+    pub async fn get_map<I>(&self, ips: I) -> Result<MapReport, IpError>
+    where
+        I: IntoIterator,
+        I::Item: ToString,
+    {
+        let ips = Self::collect_upload(ips)?;
+
+        let request = self
+            .client
+            .post(format!("{}/tools/map?cli=1", self.base_url))
+            .headers(Self::construct_headers())
+            .json(&json!(ips));
+        let raw_resp = send(
+            &self.client,
+            &self.middleware,
+            self.metrics.as_deref(),
+            &self.retry,
+            "map",
+            request,
+        )
+        .await?;
 
         // Parse the response
         let resp: serde_json::Value = serde_json::from_str(&raw_resp)?;
@@ -374,16 +704,67 @@ impl IpInfo {
             return Err(err!(IpRequestError, e));
         }
 
-        // Parse the results and add additional country details
-        let mut details: IpDetails = serde_json::from_str(&raw_resp)?;
-        self.populate_static_details(&mut details);
+        // Parse the results
+        let report: MapReport = serde_json::from_str(&raw_resp)?;
+        Ok(report)
+    }
 
-        // update cache
-        self.cache.put(cache_key(ip), details.clone());
-        Ok(details)
+    /// Get summary statistics of a list of IPs
+    ///
+    /// Accepts any iterator of displayable IPs, such as `&str` or `IpAddr`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ipinfo::IpInfo;
+    ///
+    ///  #[tokio::main]
+    /// async fn main() {
+    ///     let ipinfo = IpInfo::new(Default::default()).expect("should construct");
+    ///     let summary = ipinfo.summarize_ips(&["8.8.8.8", "4.2.2.4"]).await.expect("should run");
+    /// }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self, ips), err)
+    )]
+    pub async fn summarize_ips<I>(&self, ips: I) -> Result<IpSummary, IpError>
+    where
+        I: IntoIterator,
+        I::Item: ToString,
+    {
+        let ips = Self::collect_upload(ips)?;
+
+        let request = self
+            .client
+            .post(format!("{}/summarize?cli=1", self.base_url))
+            .headers(Self::construct_headers())
+            .token_auth(self.token.as_ref())
+            .json(&json!(ips));
+        let raw_resp = send(
+            &self.client,
+            &self.middleware,
+            self.metrics.as_deref(),
+            &self.retry,
+            "summarize",
+            request,
+        )
+        .await?;
+
+        // Parse the response
+        let resp: serde_json::Value = serde_json::from_str(&raw_resp)?;
+
+        // Return if an error occurred
+        if let Some(e) = resp["error"].as_str() {
+            return Err(err!(IpRequestError, e));
+        }
+
+        // Parse the results
+        let summary: IpSummary = serde_json::from_str(&raw_resp)?;
+        Ok(summary)
     }
 
-    /// Get a mapping of a list of IPs on a world map
+    /// Looks up the IP ranges owned by a domain
     ///
     /// # Example
     ///
@@ -393,28 +774,106 @@ impl IpInfo {
     ///  #[tokio::main]
     /// async fn main() {
     ///     let ipinfo = IpInfo::new(Default::default()).expect("should construct");
-    ///     let map_url = ipinfo.get_map(&["8.8.8.8", "4.2.2.4"]).await.expect("should run");
+    ///     let ranges = ipinfo.lookup_ranges("comcast.net").await.expect("should run");
     /// }
     /// ```
-    pub async fn get_map(&self, ips: &[&str]) -> Result<String, IpError> {
-        if ips.len() > 500_000 {
-            return Err(err!(MapLimitError));
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), err)
+    )]
+    pub async fn lookup_ranges(
+        &self,
+        domain: &str,
+    ) -> Result<Vec<IpNetwork>, IpError> {
+        if matches!(domain, "" | "." | "..") {
+            return Err(err!(
+                IpRequestError,
+                &format!("invalid domain {domain:?}")
+            ));
+        }
+
+        // Encode the domain as a single path segment
+        let mut url = Url::parse(&self.base_url)
+            .map_err(|e| err!(ConfigError, &e.to_string()))?;
+        url.path_segments_mut()
+            .map_err(|_| err!(ConfigError, "base URL cannot have a path"))?
+            .pop_if_empty()
+            .push("ranges")
+            .push(domain);
+
+        let request = self
+            .client
+            .get(url)
+            .headers(Self::construct_headers())
+            .token_auth(self.token.as_ref());
+        let raw_resp = send(
+            &self.client,
+            &self.middleware,
+            self.metrics.as_deref(),
+            &self.retry,
+            "ranges",
+            request,
+        )
+        .await?;
+
+        // Parse the response
+        let resp: serde_json::Value = serde_json::from_str(&raw_resp)?;
+
+        // Return if an error occurred
+        if let Some(e) = resp["error"].as_str() {
+            return Err(err!(IpRequestError, e));
         }
 
-        let map_url = &format!("{BASE_URL}/tools/map?cli=1");
-        let client = self.client.clone();
-        let json_ips = serde_json::json!(ips);
+        // Parse the results
+        let details: RangesDetails = serde_json::from_str(&raw_resp)?;
+        Ok(details.ranges)
+    }
+
+    /// Get the plan, request quota and features of the access token
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ipinfo::IpInfo;
+    ///
+    ///  #[tokio::main]
+    /// async fn main() {
+    ///     let ipinfo = IpInfo::new(Default::default()).expect("should construct");
+    ///     let account = ipinfo.account_info().await.expect("should run");
+    ///     println!("{} requests remaining", account.requests.remaining);
+    /// }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), err)
+    )]
+    pub async fn account_info(&self) -> Result<AccountInfo, IpError> {
+        let request = self
+            .client
+            .get(format!("{}/me", self.base_url))
+            .headers(Self::construct_headers())
+            .token_auth(self.token.as_ref());
+        let raw_resp = send(
+            &self.client,
+            &self.middleware,
+            self.metrics.as_deref(),
+            &self.retry,
+            "me",
+            request,
+        )
+        .await?;
+
+        // Parse the response
+        let resp: serde_json::Value = serde_json::from_str(&raw_resp)?;
 
-        let response = client.post(map_url).json(&json_ips).send().await?;
-        if !response.status().is_success() {
-            return Err(err!(HTTPClientError));
+        // Return if an error occurred
+        if let Some(e) = resp["error"].as_str() {
+            return Err(err!(IpRequestError, e));
         }
 
-        let response_json: serde_json::Value = response.json().await?;
-        let report_url = response_json["reportUrl"]
-            .as_str()
-            .ok_or("Report URL not found");
-        Ok(report_url.unwrap().to_string())
+        // Parse the results
+        let account: AccountInfo = serde_json::from_str(&raw_resp)?;
+        Ok(account)
     }
 
     /// Looks up residential proxy details for a single IP address
@@ -430,25 +889,28 @@ impl IpInfo {
     ///     let res = ipinfo.lookup_resproxy("175.107.211.204").await.expect("should run");
     /// }
     /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), err)
+    )]
     pub async fn lookup_resproxy(
         &self,
         ip: &str,
     ) -> Result<ResproxyDetails, IpError> {
-        let response = self
+        let request = self
             .client
             .get(format!("{}/resproxy/{ip}", self.base_url))
             .headers(Self::construct_headers())
-            .bearer_auth(self.token.as_deref().unwrap_or_default())
-            .send()
-            .await?;
-
-        // Check if we exhausted our request quota
-        if let reqwest::StatusCode::TOO_MANY_REQUESTS = response.status() {
-            return Err(err!(RateLimitExceededError));
-        }
-
-        // Acquire response
-        let raw_resp = response.error_for_status()?.text().await?;
+            .token_auth(self.token.as_ref());
+        let raw_resp = send(
+            &self.client,
+            &self.middleware,
+            self.metrics.as_deref(),
+            &self.retry,
+            "resproxy",
+            request,
+        )
+        .await?;
 
         // Parse the response
         let resp: serde_json::Value = serde_json::from_str(&raw_resp)?;
@@ -463,25 +925,21 @@ impl IpInfo {
         Ok(details)
     }
 
-    // Add country details and EU status to response
-    fn populate_static_details(&self, details: &mut IpDetails) {
-        if !&details.country.is_empty() {
-            let country_name = self.countries.get(&details.country).unwrap();
-            details.country_name = Some(country_name.to_string());
-            details.is_eu = Some(self.eu.contains(&details.country));
-            let country_flag =
-                self.country_flags.get(&details.country).unwrap();
-            details.country_flag = Some(country_flag.to_owned());
-            let file_ext = ".svg";
-            details.country_flag_url = Some(
-                COUNTRY_FLAG_URL.to_string() + &details.country + file_ext,
-            );
-            let country_currency =
-                self.country_currencies.get(&details.country).unwrap();
-            details.country_currency = Some(country_currency.to_owned());
-            let continent = self.continents.get(&details.country).unwrap();
-            details.continent = Some(continent.to_owned());
+    // Collect IPs to upload, enforcing the maximum upload size
+    fn collect_upload<I>(ips: I) -> Result<Vec<String>, IpError>
+    where
+        I: IntoIterator,
+        I::Item: ToString,
+    {
+        let ips: Vec<String> = ips
+            .into_iter()
+            .take(UPLOAD_MAX_SIZE + 1)
+            .map(|ip| ip.to_string())
+            .collect();
+        if ips.len() > UPLOAD_MAX_SIZE {
+            return Err(err!(MapLimitError));
         }
+        Ok(ips)
     }
 
     /// Construct API request headers.
@@ -504,14 +962,14 @@ impl IpInfo {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::IpErrorKind;
+    use crate::{IpErrorKind, ResponseInfo};
     use std::env;
-    use wiremock::matchers::{method, path};
+    use wiremock::matchers::{body_json, header, method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     fn get_ipinfo_client() -> IpInfo {
         IpInfo::new(IpInfoConfig {
-            token: Some(env::var("IPINFO_TOKEN").unwrap().to_string()),
+            token: Some(env::var("IPINFO_TOKEN").unwrap().into()),
             timeout: Duration::from_secs(3),
             cache_size: 100,
             ..Default::default()
@@ -673,7 +1131,7 @@ mod tests {
             .await;
 
         let ipinfo = IpInfo::new(IpInfoConfig {
-            token: Some("test_token".to_string()),
+            token: Some("test_token".into()),
             base_url: Some(mock_server.uri()),
             ..Default::default()
         })
@@ -691,33 +1149,1052 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn request_resproxy_empty() {
+    async fn request_base_url() {
         let mock_server = MockServer::start().await;
 
         Mock::given(method("GET"))
-            .and(path("/resproxy/8.8.8.8"))
-            .respond_with(
-                ResponseTemplate::new(200)
-                    .set_body_json(serde_json::json!({})),
-            )
+            .and(path("/8.8.8.8"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "ip": "8.8.8.8",
+                    "city": "Mountain View",
+                    "region": "California",
+                    "country": "US",
+                    "loc": "37.4056,-122.0775"
+                }),
+            ))
             .mount(&mock_server)
             .await;
 
-        let ipinfo = IpInfo::new(IpInfoConfig {
-            token: Some("test_token".to_string()),
+        Mock::given(method("POST"))
+            .and(path("/batch"))
+            .and(body_json(serde_json::json!(["4.2.2.4"])))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "4.2.2.4": {
+                        "ip": "4.2.2.4",
+                        "city": "Monroe",
+                        "region": "Louisiana",
+                        "country": "US",
+                        "loc": "32.5530,-92.0422"
+                    }
+                }),
+            ))
+            .mount(&mock_server)
+            .await;
+
+        let mut ipinfo = IpInfo::new(IpInfoConfig {
+            token: Some("test_token".into()),
             base_url: Some(mock_server.uri()),
             ..Default::default()
         })
         .expect("should construct");
 
+        let details = ipinfo.lookup("8.8.8.8").await.expect("should lookup");
+        assert_eq!(details.city, "Mountain View");
+        assert_eq!(details.country_name, Some("United States".to_string()));
+
         let details = ipinfo
-            .lookup_resproxy("8.8.8.8")
+            .lookup_batch(&["8.8.8.8", "4.2.2.4"], BatchReqOpts::default())
             .await
-            .expect("should lookup resproxy");
+            .expect("should lookup");
+        assert_eq!(details["8.8.8.8"].city, "Mountain View");
+        assert_eq!(details["4.2.2.4"].city, "Monroe");
+    }
 
-        assert!(details.ip.is_empty());
-        assert!(details.last_seen.is_none());
-        assert!(details.percent_days_seen.is_none());
-        assert!(details.service.is_none());
+    #[tokio::test]
+    async fn request_custom_client() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/8.8.8.8"))
+            .and(header("x-client", "custom"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "ip": "8.8.8.8",
+                    "city": "Mountain View",
+                    "region": "California",
+                    "country": "US",
+                    "loc": "37.4056,-122.0775"
+                }),
+            ))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/batch"))
+            .and(header("x-client", "custom"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "4.2.2.4": {
+                        "ip": "4.2.2.4",
+                        "city": "Monroe",
+                        "region": "Louisiana",
+                        "country": "US",
+                        "loc": "32.5530,-92.0422"
+                    }
+                }),
+            ))
+            .mount(&mock_server)
+            .await;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-client", HeaderValue::from_static("custom"));
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .expect("should build client");
+
+        let mut ipinfo = IpInfo::new(IpInfoConfig {
+            token: Some("test_token".into()),
+            client: Some(client),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        let details = ipinfo.lookup("8.8.8.8").await.expect("should lookup");
+        assert_eq!(details.city, "Mountain View");
+
+        let details = ipinfo
+            .lookup_batch(&["4.2.2.4"], BatchReqOpts::default())
+            .await
+            .expect("should lookup");
+        assert_eq!(details["4.2.2.4"].city, "Monroe");
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        responses: std::sync::Mutex<Vec<ResponseInfo>>,
+    }
+
+    impl Middleware for Recorder {
+        fn on_request(&self, request: &mut reqwest::Request) {
+            request
+                .headers_mut()
+                .insert("x-trace-id", HeaderValue::from_static("abc123"));
+        }
+
+        fn on_response(&self, response: &ResponseInfo) {
+            self.responses.lock().unwrap().push(response.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn request_middleware() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/resproxy/8.8.8.8"))
+            .and(header("x-trace-id", "abc123"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({})),
+            )
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/resproxy/4.2.2.4"))
+            .respond_with(ResponseTemplate::new(429))
+            .mount(&mock_server)
+            .await;
+
+        let recorder = Arc::new(Recorder::default());
+        let ipinfo = IpInfo::new(IpInfoConfig {
+            token: Some("test_token".into()),
+            middleware: vec![recorder.clone()],
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        ipinfo
+            .lookup_resproxy("8.8.8.8")
+            .await
+            .expect("should lookup resproxy");
+        assert_eq!(
+            ipinfo
+                .lookup_resproxy("4.2.2.4")
+                .await
+                .err()
+                .unwrap()
+                .kind(),
+            IpErrorKind::RateLimitExceededError
+        );
+
+        let responses = recorder.responses.lock().unwrap();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].method, reqwest::Method::GET);
+        assert_eq!(responses[0].url.path(), "/resproxy/8.8.8.8");
+        assert_eq!(responses[0].status, reqwest::StatusCode::OK);
+        assert_eq!(responses[0].body_size, 2);
+        assert_eq!(
+            responses[1].status,
+            reqwest::StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+
+    #[derive(Default)]
+    struct MetricsRecorder {
+        requests: std::sync::Mutex<Vec<(String, u16)>>,
+        cache: std::sync::Mutex<Vec<bool>>,
+        batches: std::sync::Mutex<Vec<usize>>,
+    }
+
+    impl MetricsSink for MetricsRecorder {
+        fn record_request(
+            &self,
+            endpoint: &str,
+            status: u16,
+            _latency: std::time::Duration,
+        ) {
+            self.requests
+                .lock()
+                .unwrap()
+                .push((endpoint.to_string(), status));
+        }
+
+        fn record_cache(&self, hit: bool) {
+            self.cache.lock().unwrap().push(hit);
+        }
+
+        fn record_batch(&self, size: usize) {
+            self.batches.lock().unwrap().push(size);
+        }
+    }
+
+    #[tokio::test]
+    async fn request_metrics() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/batch"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "8.8.8.8": {
+                        "ip": "8.8.8.8",
+                        "city": "Mountain View",
+                        "region": "California",
+                        "country": "US",
+                        "loc": "37.4056,-122.0775"
+                    },
+                    "1.1.1.1": {
+                        "ip": "1.1.1.1",
+                        "city": "Brisbane",
+                        "region": "Queensland",
+                        "country": "AU",
+                        "loc": "-27.4820,153.0136"
+                    }
+                }),
+            ))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/4.2.2.4"))
+            .respond_with(ResponseTemplate::new(429))
+            .mount(&mock_server)
+            .await;
+
+        let metrics = Arc::new(MetricsRecorder::default());
+        let mut ipinfo = IpInfo::new(IpInfoConfig {
+            token: Some("test_token".into()),
+            metrics: Some(metrics.clone()),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        ipinfo
+            .lookup_batch(&["8.8.8.8", "1.1.1.1"], BatchReqOpts::default())
+            .await
+            .expect("should lookup");
+        ipinfo.lookup("8.8.8.8").await.expect("should lookup");
+        assert!(ipinfo.lookup("4.2.2.4").await.is_err());
+
+        assert_eq!(
+            *metrics.requests.lock().unwrap(),
+            vec![("batch".to_string(), 200), ("lookup".to_string(), 429)]
+        );
+        assert_eq!(
+            *metrics.cache.lock().unwrap(),
+            vec![false, false, true, false]
+        );
+        assert_eq!(*metrics.batches.lock().unwrap(), vec![2]);
+    }
+
+    #[tokio::test]
+    async fn request_account_info() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/me"))
+            .and(header("authorization", "Bearer test_token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "token": "test_token",
+                    "plan": "Basic",
+                    "requests": {
+                        "day": 120,
+                        "month": 3400,
+                        "limit": 150000,
+                        "remaining": 146600
+                    },
+                    "features": {
+                        "core": {"daily": 5000, "monthly": 150000},
+                        "ranges": {"daily": 100, "monthly": 1000}
+                    }
+                }),
+            ))
+            .mount(&mock_server)
+            .await;
+
+        let ipinfo = IpInfo::new(IpInfoConfig {
+            token: Some("test_token".into()),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        let account = ipinfo
+            .account_info()
+            .await
+            .expect("should get account info");
+        assert_eq!(account.plan.as_deref(), Some("Basic"));
+        assert_eq!(account.requests.day, 120);
+        assert_eq!(account.requests.month, 3400);
+        assert_eq!(account.requests.limit, 150000);
+        assert_eq!(account.requests.remaining, 146600);
+        assert!(account.features.contains_key("core"));
+        assert!(account.features.contains_key("ranges"));
+    }
+
+    #[tokio::test]
+    async fn request_without_token() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/resproxy/8.8.8.8"))
+            .and(|request: &wiremock::Request| {
+                !request.headers.contains_key("authorization")
+            })
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({})),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let ipinfo = IpInfo::new(IpInfoConfig {
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        ipinfo
+            .lookup_resproxy("8.8.8.8")
+            .await
+            .expect("should lookup resproxy");
+    }
+
+    #[tokio::test]
+    async fn request_retry() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/resproxy/8.8.8.8"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/resproxy/8.8.8.8"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({"ip": "8.8.8.8", "service": "Test"}),
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/resproxy/4.2.2.4"))
+            .respond_with(ResponseTemplate::new(429))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let ipinfo = IpInfo::new(IpInfoConfig {
+            token: Some("test_token".into()),
+            retry: RetryPolicy {
+                max_retries: 2,
+                initial_backoff: Duration::from_millis(1),
+                ..Default::default()
+            },
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        let details = ipinfo
+            .lookup_resproxy("8.8.8.8")
+            .await
+            .expect("should lookup resproxy");
+        assert_eq!(details.service.as_deref(), Some("Test"));
+
+        // The rate limit error is returned once the retries are exhausted
+        let ipinfo = IpInfo::new(IpInfoConfig {
+            retry: RetryPolicy {
+                max_retries: 1,
+                initial_backoff: Duration::from_millis(1),
+                ..Default::default()
+            },
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+        assert_eq!(
+            ipinfo
+                .lookup_resproxy("4.2.2.4")
+                .await
+                .err()
+                .unwrap()
+                .kind(),
+            IpErrorKind::RateLimitExceededError
+        );
+    }
+
+    #[tokio::test]
+    async fn request_field() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/8.8.8.8/country"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("US\n"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut ipinfo = IpInfo::new(IpInfoConfig {
+            token: Some("test_token".into()),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        // The second lookup should be served from the cache
+        for _ in 0..2 {
+            let country = ipinfo
+                .lookup_field("8.8.8.8", IpField::Country)
+                .await
+                .expect("should lookup field");
+            assert_eq!(country.as_deref(), Some("US"));
+        }
+
+        let bogon = ipinfo
+            .lookup_field("10.0.0.1", IpField::City)
+            .await
+            .expect("should lookup field");
+        assert_eq!(bogon, None);
+    }
+
+    #[tokio::test]
+    async fn request_field_cache() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/8.8.8.8/postal"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string("undefined\n"),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/8.8.8.8/city"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string("Mountain View"),
+            )
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/8.8.8.8/org"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(
+                serde_json::json!({"error": "Field not available"}),
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/8.8.4.4"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "ip": "8.8.4.4",
+                    "city": "Mountain View",
+                    "region": "California",
+                    "country": "US",
+                    "loc": "37.4056,-122.0775",
+                }),
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut ipinfo = IpInfo::new(IpInfoConfig {
+            token: Some("test_token".into()),
+            negative_cache_ttl: Some(Duration::from_secs(60)),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        // Fields without a value are cached as None
+        for _ in 0..2 {
+            let postal = ipinfo
+                .lookup_field("8.8.8.8", IpField::Postal)
+                .await
+                .expect("should lookup field");
+            assert_eq!(postal, None);
+        }
+
+        // Failures which would repeat are cached
+        for _ in 0..2 {
+            let e = ipinfo
+                .lookup_field("8.8.8.8", IpField::Org)
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(e.status(), Some(404));
+        }
+
+        // Fields of cached details are served without a request
+        ipinfo.lookup("8.8.4.4").await.expect("should lookup");
+        let region = ipinfo
+            .lookup_field("8.8.4.4", IpField::Region)
+            .await
+            .expect("should lookup field");
+        assert_eq!(region.as_deref(), Some("California"));
+
+        // Expired values are looked up again
+        let mut ipinfo = IpInfo::new(IpInfoConfig {
+            cache_ttl: Some(CacheTtl {
+                soft: Duration::ZERO,
+                hard: Duration::ZERO,
+            }),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+        for _ in 0..2 {
+            let city = ipinfo
+                .lookup_field("8.8.8.8", IpField::City)
+                .await
+                .expect("should lookup field");
+            assert_eq!(city.as_deref(), Some("Mountain View"));
+        }
+    }
+
+    #[tokio::test]
+    async fn request_map() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/tools/map"))
+            .and(body_json(serde_json::json!(["8.8.8.8", "2001:4860::8888"])))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "status": "Report Generated",
+                    "reportUrl": "https://ipinfo.io/tools/map/8a7e"
+                }),
+            ))
+            .mount(&mock_server)
+            .await;
+
+        let ipinfo = IpInfo::new(IpInfoConfig {
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        let ips: Vec<std::net::IpAddr> = vec![
+            "8.8.8.8".parse().unwrap(),
+            "2001:4860::8888".parse().unwrap(),
+        ];
+        let report = ipinfo.get_map(ips).await.expect("should get map");
+
+        assert_eq!(report.status, Some("Report Generated".to_string()));
+        assert_eq!(report.report_url, "https://ipinfo.io/tools/map/8a7e");
+    }
+
+    #[tokio::test]
+    async fn request_map_errors() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/tools/map"))
+            .and(body_json(serde_json::json!(["8.8.8.8"])))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"status": "ok"})),
+            )
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/tools/map"))
+            .and(body_json(serde_json::json!(["4.2.2.4"])))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock_server)
+            .await;
+
+        let ipinfo = IpInfo::new(IpInfoConfig {
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        // A response without a report URL is a parse error
+        let err = ipinfo.get_map(&["8.8.8.8"]).await.err().unwrap();
+        assert_eq!(err.kind(), IpErrorKind::ParseError);
+
+        // The HTTP status is reported on failure
+        let err = ipinfo.get_map(&["4.2.2.4"]).await.err().unwrap();
+        assert_eq!(err.kind(), IpErrorKind::HTTPClientError);
+        assert!(err.to_string().contains("500"));
+    }
+
+    #[tokio::test]
+    async fn request_summary() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/summarize"))
+            .and(body_json(serde_json::json!(["8.8.8.8", "1.1.1.1"])))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "total": 2,
+                    "unique": 2,
+                    "countries": {"US": 1, "AU": 1},
+                    "asns": {"AS15169 Google LLC": 1, "AS13335 Cloudflare, Inc.": 1},
+                    "ipTypes": {"hosting": 2},
+                    "privacy": {"vpn": 0, "proxy": 0, "hosting": 2, "relay": 0, "tor": 0},
+                    "anycast": 2
+                }),
+            ))
+            .mount(&mock_server)
+            .await;
+
+        let ipinfo = IpInfo::new(IpInfoConfig {
+            token: Some("test_token".into()),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        let summary = ipinfo
+            .summarize_ips(&["8.8.8.8", "1.1.1.1"])
+            .await
+            .expect("should summarize");
+
+        assert_eq!(summary.total, 2);
+        assert_eq!(summary.unique, 2);
+        assert_eq!(summary.countries["US"], 1);
+        assert_eq!(summary.countries["AU"], 1);
+        assert_eq!(summary.asns["AS15169 Google LLC"], 1);
+        assert_eq!(summary.ip_types["hosting"], 2);
+        assert_eq!(summary.privacy.hosting, 2);
+        assert_eq!(summary.anycast, 2);
+        assert!(summary.carriers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn request_summary_limit() {
+        let ipinfo =
+            IpInfo::new(Default::default()).expect("should construct");
+        let ips = vec!["8.8.8.8"; UPLOAD_MAX_SIZE + 1];
+
+        assert_eq!(
+            ipinfo.summarize_ips(&ips).await.err().unwrap().kind(),
+            IpErrorKind::MapLimitError
+        );
+    }
+
+    #[tokio::test]
+    async fn request_ranges() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/ranges/comcast.net"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "domain": "comcast.net",
+                    "num_ranges": "2",
+                    "ranges": ["23.24.240.0/29", "2601::/20"]
+                }),
+            ))
+            .mount(&mock_server)
+            .await;
+
+        let ipinfo = IpInfo::new(IpInfoConfig {
+            token: Some("test_token".into()),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        let ranges = ipinfo
+            .lookup_ranges("comcast.net")
+            .await
+            .expect("should lookup ranges");
+
+        assert_eq!(
+            ranges,
+            vec![
+                "23.24.240.0/29".parse::<IpNetwork>().unwrap(),
+                "2601::/20".parse::<IpNetwork>().unwrap(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn request_ranges_invalid() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/ranges/example.com"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "domain": "example.com",
+                    "ranges": ["not a range"]
+                }),
+            ))
+            .mount(&mock_server)
+            .await;
+
+        let ipinfo = IpInfo::new(IpInfoConfig {
+            token: Some("test_token".into()),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        assert_eq!(
+            ipinfo
+                .lookup_ranges("example.com")
+                .await
+                .err()
+                .unwrap()
+                .kind(),
+            IpErrorKind::ParseError
+        );
+    }
+
+    #[tokio::test]
+    async fn request_ranges_encoded() {
+        let mock_server = MockServer::start().await;
+
+        for encoded in ["/ranges/a%2Fb%3Fx", "/ranges/..%2Fme"] {
+            Mock::given(method("GET"))
+                .and(path(encoded))
+                .respond_with(ResponseTemplate::new(200).set_body_json(
+                    serde_json::json!({ "ranges": ["23.24.240.0/29"] }),
+                ))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+
+        let ipinfo = IpInfo::new(IpInfoConfig {
+            token: Some("test_token".into()),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        // Domains stay within the ranges endpoint
+        for domain in ["a/b?x", "../me"] {
+            let ranges = ipinfo
+                .lookup_ranges(domain)
+                .await
+                .expect("should lookup ranges");
+            assert_eq!(ranges.len(), 1);
+        }
+        for domain in ["", ".", ".."] {
+            assert_eq!(
+                ipinfo.lookup_ranges(domain).await.err().unwrap().kind(),
+                IpErrorKind::IpRequestError
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn request_resproxy_empty() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/resproxy/8.8.8.8"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({})),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let ipinfo = IpInfo::new(IpInfoConfig {
+            token: Some("test_token".into()),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        let details = ipinfo
+            .lookup_resproxy("8.8.8.8")
+            .await
+            .expect("should lookup resproxy");
+
+        assert!(details.ip.is_empty());
+        assert!(details.last_seen.is_none());
+        assert!(details.percent_days_seen.is_none());
+        assert!(details.service.is_none());
+    }
+
+    #[tokio::test]
+    async fn request_prefix_cache() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/8.8.8.8"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "ip": "8.8.8.8",
+                    "hostname": "dns.google",
+                    "city": "Mountain View",
+                    "region": "California",
+                    "country": "US",
+                    "loc": "37.4056,-122.0775",
+                    "asn": {
+                        "asn": "AS15169",
+                        "name": "Google LLC",
+                        "domain": "google.com",
+                        "route": "8.8.8.0/24",
+                        "type": "hosting"
+                    }
+                }),
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut ipinfo = IpInfo::new(IpInfoConfig {
+            token: Some("test_token".into()),
+            cache_mode: CacheMode::Prefix,
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        ipinfo.lookup("8.8.8.8").await.expect("should lookup");
+
+        // Served from the cached route without a request
+        let details = ipinfo.lookup("8.8.8.4").await.expect("should lookup");
+        assert_eq!(details.ip, "8.8.8.4");
+        assert_eq!(details.city, "Mountain View");
+        assert_eq!(details.country_name.as_deref(), Some("United States"));
+        assert!(details.hostname.is_none());
+    }
+
+    #[tokio::test]
+    async fn request_negative_cache() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/not-an-ip"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(
+                serde_json::json!({
+                    "status": 404,
+                    "error": {
+                        "title": "Wrong ip",
+                        "message": "Please provide a valid IP address"
+                    }
+                }),
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/8.8.8.8"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let mut ipinfo = IpInfo::new(IpInfoConfig {
+            token: Some("test_token".into()),
+            negative_cache_ttl: Some(Duration::from_secs(60)),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        let first = ipinfo.lookup("not-an-ip").await.err().unwrap();
+        assert_eq!(first.status(), Some(404));
+        let second = ipinfo.lookup("not-an-ip").await.err().unwrap();
+        assert_eq!(first, second);
+
+        // Server errors may succeed later and are not cached
+        for _ in 0..2 {
+            let e = ipinfo.lookup("8.8.8.8").await.err().unwrap();
+            assert_eq!(e.status(), Some(503));
+        }
+    }
+
+    #[tokio::test]
+    async fn request_stale_while_revalidate() {
+        let mock_server = MockServer::start().await;
+
+        let response = |city: &str| {
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "ip": "8.8.8.8",
+                "city": city,
+                "region": "California",
+                "country": "US",
+                "loc": "37.4056,-122.0775"
+            }))
+        };
+        Mock::given(method("GET"))
+            .and(path("/8.8.8.8"))
+            .respond_with(response("Mountain View"))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/8.8.8.8"))
+            .respond_with(response("Palo Alto"))
+            .mount(&mock_server)
+            .await;
+
+        let mut ipinfo = IpInfo::new(IpInfoConfig {
+            token: Some("test_token".into()),
+            cache_ttl: Some(CacheTtl {
+                soft: Duration::ZERO,
+                hard: Duration::from_secs(60),
+            }),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        let details = ipinfo.lookup("8.8.8.8").await.expect("should lookup");
+        assert_eq!(details.city, "Mountain View");
+
+        // The stale result is returned while it is refreshed
+        let mut details =
+            ipinfo.lookup("8.8.8.8").await.expect("should lookup");
+        assert_eq!(details.city, "Mountain View");
+
+        // The refreshed result replaces it once the refresh completes
+        for _ in 0..100 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            details = ipinfo.lookup("8.8.8.8").await.expect("should lookup");
+            if details.city != "Mountain View" {
+                break;
+            }
+        }
+        assert_eq!(details.city, "Palo Alto");
+
+        // Expired results are looked up again before returning
+        let mut ipinfo = IpInfo::new(IpInfoConfig {
+            cache_ttl: Some(CacheTtl {
+                soft: Duration::ZERO,
+                hard: Duration::ZERO,
+            }),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+        ipinfo.lookup("8.8.8.8").await.expect("should lookup");
+        let before = mock_server.received_requests().await.unwrap().len();
+        ipinfo.lookup("8.8.8.8").await.expect("should lookup");
+        let after = mock_server.received_requests().await.unwrap().len();
+        assert_eq!(after, before + 1);
+    }
+
+    #[tokio::test]
+    async fn request_export_import_cache() {
+        let mock_server = MockServer::start().await;
+
+        for (ip, asn) in [("8.8.8.8", "AS15169"), ("1.1.1.1", "AS13335")] {
+            Mock::given(method("GET"))
+                .and(path(format!("/{ip}")))
+                .respond_with(ResponseTemplate::new(200).set_body_json(
+                    serde_json::json!({
+                        "ip": ip,
+                        "city": "Mountain View",
+                        "region": "California",
+                        "country": "US",
+                        "loc": "37.4056,-122.0775",
+                        "org": format!("{asn} Test")
+                    }),
+                ))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "ip": "4.2.2.4",
+                    "city": "Mountain View",
+                    "region": "California",
+                    "country": "US",
+                    "loc": "37.4056,-122.0775",
+                    "org": "AS3356 Test"
+                }),
+            ))
+            .mount(&mock_server)
+            .await;
+
+        let config = || IpInfoConfig {
+            token: Some("test_token".into()),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        };
+
+        let mut ipinfo = IpInfo::new(config()).expect("should construct");
+        ipinfo.lookup("8.8.8.8").await.expect("should lookup");
+        ipinfo.lookup("1.1.1.1").await.expect("should lookup");
+        ipinfo.lookup_self_v4().await.expect("should lookup");
+
+        let mut lines = Vec::new();
+        ipinfo
+            .export_cache()
+            .write_json_lines(&mut lines)
+            .expect("should write");
+        let snapshot =
+            CacheSnapshot::<IpDetails>::read_json_lines(lines.as_slice())
+                .expect("should read");
+
+        // Least recently used first, without the client's own IP
+        let keys: Vec<_> =
+            snapshot.entries.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, ["8.8.8.8:1", "1.1.1.1:1"]);
+
+        // The new client is served from the imported cache
+        let mut ipinfo = IpInfo::new(config()).expect("should construct");
+        assert_eq!(ipinfo.import_cache(snapshot), 2);
+        let details = ipinfo.lookup("1.1.1.1").await.expect("should lookup");
+        assert_eq!(details.org.as_deref(), Some("AS13335 Test"));
+        assert_eq!(details.country_name.as_deref(), Some("United States"));
+        assert_eq!(details.continent.unwrap().code, "NA");
     }
 }
@@ -12,37 +12,76 @@
 //   See the License for the specific language governing permissions and
 //   limitations under the License.
 
-use std::{collections::HashMap, num::NonZeroUsize, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use crate::{
-    cache_key, is_bogon, Continent, CountryCurrency, CountryFlag,
-    IpDetailsLite, IpError, CONTINENTS, COUNTRIES, CURRENCIES, EU, FLAGS,
-    VERSION,
+    cache::{restored_entries, DetailsCache, Refreshes},
+    enrich::CountryData,
+    is_bogon,
+    metrics::record_cache,
+    middleware::send,
+    shared::{cache_details, shared_lookup},
+    token::TokenAuth,
+    util::validate_config,
+    CacheMode, CacheSnapshot, CacheTtl, Continent, CountryCurrency,
+    CountryFlag, IpDetailsLite, IpError, MetricsSink, Middleware, RetryPolicy,
+    SharedCache, Token, VERSION,
 };
 
-use lru::LruCache;
-
-use reqwest::header::{
-    HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE, USER_AGENT,
+use reqwest::{
+    header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE, USER_AGENT},
+    RequestBuilder,
 };
 
-const COUNTRY_FLAG_URL: &str =
-    "https://cdn.ipinfo.io/static/images/countries-flags/";
 const BASE_URL: &str = "https://api.ipinfo.io/lite";
 const BASE_URL_V6: &str = "https://v6.api.ipinfo.io/lite";
 
 /// IpInfoLite structure configuration.
 pub struct IpInfoLiteConfig {
     /// IPinfo access token.
-    pub token: Option<String>,
+    pub token: Option<Token>,
 
     /// The timeout of HTTP requests. (default: 3 seconds)
     pub timeout: Duration,
 
+    /// HTTP client to send all requests with, e.g. one configured with a
+    /// proxy or custom root certificates. `timeout` is not applied to a
+    /// provided client. (default: a new client)
+    pub client: Option<reqwest::Client>,
+
+    /// Middleware applied to every request, in order. (default: none)
+    pub middleware: Vec<Arc<dyn Middleware>>,
+
+    /// Sink receiving request, cache and batch metrics. (default: none)
+    pub metrics: Option<Arc<dyn MetricsSink>>,
+
+    /// Retry policy for failed requests. (default: no retries)
+    pub retry: RetryPolicy,
+
     /// The size of the LRU cache. (default: 100 IPs)
     pub cache_size: usize,
 
+    /// How lookup results are cached. (default: by IP address)
+    pub cache_mode: CacheMode,
+
+    /// How long lookup results are cached, and when they are refreshed in
+    /// the background. (default: until evicted)
+    pub cache_ttl: Option<CacheTtl>,
+
+    /// Cache shared with the clients of other tiers. (default: none)
+    pub shared_cache: Option<SharedCache>,
+
+    /// How long failed lookups that would fail again, such as an invalid
+    /// IP address, are cached and return the same error without a request.
+    /// (default: not cached)
+    pub negative_cache_ttl: Option<Duration>,
+
     // Default mapping of country codes to country names
+    pub default_countries: Option<HashMap<String, String>>,
+
+    /// Former, misspelled name of `default_countries`, used when that is
+    /// not set.
+    #[deprecated(note = "use `default_countries` instead")]
     pub defaut_countries: Option<HashMap<String, String>>,
 
     // Default list of EU countries
@@ -56,6 +95,13 @@ pub struct IpInfoLiteConfig {
 
     // Default mapping of country codes to their respective continent code and name
     pub default_continents: Option<HashMap<String, Continent>>,
+
+    /// Base URL for API requests. (default: https://api.ipinfo.io/lite)
+    pub base_url: Option<String>,
+
+    /// Base URL for API requests over IPv6, used to look up your own v6 IP.
+    /// (default: https://v6.api.ipinfo.io/lite)
+    pub base_url_v6: Option<String>,
 }
 
 impl Default for IpInfoLiteConfig {
@@ -63,26 +109,41 @@ impl Default for IpInfoLiteConfig {
         Self {
             token: None,
             timeout: Duration::from_secs(3),
+            client: None,
+            middleware: Vec::new(),
+            metrics: None,
+            retry: RetryPolicy::default(),
             cache_size: 100,
+            cache_mode: CacheMode::default(),
+            cache_ttl: None,
+            shared_cache: None,
+            negative_cache_ttl: None,
+            default_countries: None,
+            #[allow(deprecated)]
             defaut_countries: None,
             default_eu: None,
             default_flags: None,
             default_currencies: None,
             default_continents: None,
+            base_url: None,
+            base_url_v6: None,
         }
     }
 }
 
 /// IpInfoLite requests context structure.
 pub struct IpInfoLite {
-    token: Option<String>,
+    token: Option<Token>,
     client: reqwest::Client,
-    cache: LruCache<String, IpDetailsLite>,
-    countries: HashMap<String, String>,
-    eu: Vec<String>,
-    country_flags: HashMap<String, CountryFlag>,
-    country_currencies: HashMap<String, CountryCurrency>,
-    continents: HashMap<String, Continent>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    metrics: Option<Arc<dyn MetricsSink>>,
+    retry: RetryPolicy,
+    cache: DetailsCache<IpDetailsLite>,
+    refreshes: Refreshes,
+    shared_cache: Option<SharedCache>,
+    country_data: CountryData,
+    base_url: String,
+    base_url_v6: String,
 }
 
 impl IpInfoLite {
@@ -96,35 +157,46 @@ impl IpInfoLite {
     /// let ipinfo = IpInfoLite::new(Default::default()).expect("should construct");
     /// ```
     pub fn new(config: IpInfoLiteConfig) -> Result<Self, IpError> {
-        let client =
-            reqwest::Client::builder().timeout(config.timeout).build()?;
+        let cache_size = validate_config(
+            config.cache_size,
+            config.timeout,
+            [config.base_url.as_deref(), config.base_url_v6.as_deref()],
+        )?;
+        let country_data = CountryData::from(&config);
+        let client = match config.client {
+            Some(client) => client,
+            None => {
+                reqwest::Client::builder().timeout(config.timeout).build()?
+            }
+        };
+        let refreshes = Refreshes::new(
+            &client,
+            &config.middleware,
+            &config.metrics,
+            &config.retry,
+            "lite",
+        );
 
-        let mut ipinfo_obj = Self {
+        Ok(Self {
             client,
+            middleware: config.middleware,
+            metrics: config.metrics,
+            retry: config.retry,
             token: config.token,
-            cache: LruCache::new(
-                NonZeroUsize::new(config.cache_size).unwrap(),
+            cache: DetailsCache::new(
+                cache_size,
+                config.cache_mode,
+                config.cache_ttl,
+                config.negative_cache_ttl,
             ),
-            countries: HashMap::new(),
-            eu: Vec::new(),
-            country_flags: HashMap::new(),
-            country_currencies: HashMap::new(),
-            continents: HashMap::new(),
-        };
-
-        ipinfo_obj.countries =
-            config.defaut_countries.unwrap_or_else(|| COUNTRIES.clone());
-        ipinfo_obj.eu = config.default_eu.unwrap_or_else(|| EU.clone());
-        ipinfo_obj.country_flags =
-            config.default_flags.unwrap_or_else(|| FLAGS.clone());
-        ipinfo_obj.country_currencies = config
-            .default_currencies
-            .unwrap_or_else(|| CURRENCIES.clone());
-        ipinfo_obj.continents = config
-            .default_continents
-            .unwrap_or_else(|| CONTINENTS.clone());
-
-        Ok(ipinfo_obj)
+            refreshes,
+            shared_cache: config.shared_cache,
+            country_data,
+            base_url: config.base_url.unwrap_or_else(|| BASE_URL.to_string()),
+            base_url_v6: config
+                .base_url_v6
+                .unwrap_or_else(|| BASE_URL_V6.to_string()),
+        })
     }
 
     /// looks up IpDetailsLite for a single IP Address
@@ -144,7 +216,8 @@ impl IpInfoLite {
         &mut self,
         ip: &str,
     ) -> Result<IpDetailsLite, IpError> {
-        self._lookup(ip, BASE_URL).await
+        let base_url = self.base_url.clone();
+        self._lookup(ip, &base_url).await
     }
 
     /// looks up IPDetailsLite of your own v4 IP
@@ -161,7 +234,8 @@ impl IpInfoLite {
     /// }
     /// ```
     pub async fn lookup_self_v4(&mut self) -> Result<IpDetailsLite, IpError> {
-        self._lookup("me", BASE_URL).await
+        let base_url = self.base_url.clone();
+        self._lookup("me", &base_url).await
     }
 
     /// looks up IPDetailsLite of your own v6 IP
@@ -178,15 +252,40 @@ impl IpInfoLite {
     /// }
     /// ```
     pub async fn lookup_self_v6(&mut self) -> Result<IpDetailsLite, IpError> {
-        self._lookup("me", BASE_URL_V6).await
+        let base_url = self.base_url_v6.clone();
+        self._lookup("me", &base_url).await
+    }
+
+    /// Snapshot the cached lookup results, e.g. to start another client with
+    /// a warm cache.
+    pub fn export_cache(&self) -> CacheSnapshot<IpDetailsLite> {
+        self.cache.snapshot()
+    }
+
+    /// Preload the cache from a snapshot, returning the number of results
+    /// loaded.
+    pub fn import_cache(
+        &mut self,
+        snapshot: CacheSnapshot<IpDetailsLite>,
+    ) -> usize {
+        let mut entries = restored_entries(snapshot);
+        for entry in &mut entries {
+            self.country_data.enrich(&mut entry.details);
+        }
+        self.cache.import(entries)
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), err)
+    )]
     async fn _lookup(
         &mut self,
         ip: &str,
         base_url: &str,
     ) -> Result<IpDetailsLite, IpError> {
         if is_bogon(ip) {
+            event!(debug, "bogon address, skipping lookup");
             return Ok(IpDetailsLite {
                 ip: ip.to_string(),
                 bogon: Some(true),
@@ -194,67 +293,97 @@ impl IpInfoLite {
             });
         }
 
-        // Check for cache hit
-        let cached_detail = self.cache.get(&cache_key(ip));
+        self.apply_refreshes();
 
-        if let Some(cached_detail) = cached_detail {
-            return Ok(cached_detail.clone());
-        }
-
-        // lookup in case of a cache miss
-        let response = self
-            .client
-            .get(format!("{base_url}/{ip}"))
-            .headers(Self::construct_headers())
-            .bearer_auth(self.token.as_deref().unwrap_or_default())
-            .send()
-            .await?;
+        // Check for cache hit, refreshing stale results in the background
+        let cached_detail = self.cache.get(ip).or_else(|| {
+            shared_lookup(
+                self.shared_cache.as_ref(),
+                &mut self.cache,
+                &self.country_data,
+                ip,
+            )
+        });
 
-        // Check if we exhausted our request quota
-        if let reqwest::StatusCode::TOO_MANY_REQUESTS = response.status() {
-            return Err(err!(RateLimitExceededError));
+        if let Some(cached_detail) = cached_detail {
+            if !cached_detail.is_stale()
+                || self.refreshes.in_flight(ip)
+                || self
+                    .refreshes
+                    .refresh(ip, self.details_request(ip, base_url))
+            {
+                event!(trace, "cache hit");
+                record_cache(&self.metrics, true);
+                return Ok(cached_detail.into_inner());
+            }
         }
 
-        // Acquire response
-        let raw_resp = response.error_for_status()?.text().await?;
-
-        // Parse the response
-        let resp: serde_json::Value = serde_json::from_str(&raw_resp)?;
-
-        // Return if an error occurred
-        if let Some(e) = resp["error"].as_str() {
-            return Err(err!(IpRequestError, e));
+        // Check for a recent failure which would repeat
+        if let Some(e) = self.cache.get_error(ip) {
+            event!(trace, "negative cache hit");
+            record_cache(&self.metrics, true);
+            return Err(e);
         }
+        event!(trace, "cache miss");
+        record_cache(&self.metrics, false);
 
-        // Parse the results and add additional country details
-        let mut details: IpDetailsLite = serde_json::from_str(&raw_resp)?;
-        self.populate_static_details(&mut details);
+        // lookup in case of a cache miss
+        let details = match self.request_details(ip, base_url).await {
+            Ok(details) => details,
+            Err(e) => {
+                self.cache.put_error(ip, &e);
+                return Err(e);
+            }
+        };
 
         // update cache
-        self.cache.put(cache_key(ip), details.clone());
+        cache_details(
+            self.shared_cache.as_ref(),
+            &mut self.cache,
+            ip,
+            details.clone(),
+        );
         Ok(details)
     }
 
-    // Add country details and EU status to response
-    fn populate_static_details(&self, details: &mut IpDetailsLite) {
-        if !&details.country_code.is_empty() {
-            let country_name =
-                self.countries.get(&details.country_code).unwrap();
-            details.country_name = country_name.to_owned();
-            details.is_eu = self.eu.contains(&details.country_code);
-            let country_flag =
-                self.country_flags.get(&details.country_code).unwrap();
-            details.country_flag = country_flag.to_owned();
-            let file_ext = ".svg";
-            details.country_flag_url = COUNTRY_FLAG_URL.to_string()
-                + &details.country_code
-                + file_ext;
-            let country_currency =
-                self.country_currencies.get(&details.country_code).unwrap();
-            details.country_currency = country_currency.to_owned();
-            let continent =
-                self.continents.get(&details.country_code).unwrap();
-            details.continent = continent.to_owned();
+    // Build the request for the details of an IP address
+    fn details_request(&self, ip: &str, base_url: &str) -> RequestBuilder {
+        self.client
+            .get(format!("{base_url}/{ip}"))
+            .headers(Self::construct_headers())
+            .token_auth(self.token.as_ref())
+    }
+
+    // Request the details of an IP address from the API
+    async fn request_details(
+        &self,
+        ip: &str,
+        base_url: &str,
+    ) -> Result<IpDetailsLite, IpError> {
+        let request = self.details_request(ip, base_url);
+        let raw_resp = send(
+            &self.client,
+            &self.middleware,
+            self.metrics.as_deref(),
+            &self.retry,
+            "lite",
+            request,
+        )
+        .await?;
+        self.country_data.parse(&raw_resp)
+    }
+
+    // Cache the results of completed background refreshes
+    fn apply_refreshes(&mut self) {
+        let completed =
+            self.refreshes.completed(|raw| self.country_data.parse(raw));
+        for (ip, details) in completed {
+            cache_details(
+                self.shared_cache.as_ref(),
+                &mut self.cache,
+                &ip,
+                details,
+            );
         }
     }
 
@@ -279,11 +408,14 @@ impl IpInfoLite {
 mod tests {
     use super::*;
     use crate::IpErrorKind::HTTPClientError;
+    use crate::IpInfoPlus;
     use std::env;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
 
     fn get_ipinfo_client() -> IpInfoLite {
         IpInfoLite::new(IpInfoLiteConfig {
-            token: Some(env::var("IPINFO_TOKEN").unwrap().to_string()),
+            token: Some(env::var("IPINFO_TOKEN").unwrap().into()),
             timeout: Duration::from_secs(3),
             cache_size: 100,
             ..Default::default()
@@ -323,6 +455,61 @@ mod tests {
     }
 
     #[tokio::test]
+    async fn lookup_base_url() {
+        let mock_server = MockServer::start().await;
+        let mock_server_v6 = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/8.8.8.8"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "ip": "8.8.8.8",
+                    "asn": "AS15169",
+                    "as_name": "Google LLC",
+                    "as_domain": "google.com",
+                    "country_code": "US",
+                    "country": "United States",
+                    "continent_code": "NA",
+                    "continent": "North America"
+                }),
+            ))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/me"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "ip": "2001:4860:4860::8888",
+                    "asn": "AS15169",
+                    "as_name": "Google LLC",
+                    "as_domain": "google.com",
+                    "country_code": "US",
+                    "country": "United States"
+                }),
+            ))
+            .mount(&mock_server_v6)
+            .await;
+
+        let mut ipinfo = IpInfoLite::new(IpInfoLiteConfig {
+            token: Some("test_token".into()),
+            base_url: Some(mock_server.uri()),
+            base_url_v6: Some(mock_server_v6.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        let details = ipinfo.lookup("8.8.8.8").await.expect("should lookup");
+        assert_eq!(details.ip, "8.8.8.8");
+        assert_eq!(details.country_name, "United States");
+        assert_eq!(details.continent.code, "NA");
+
+        let details = ipinfo.lookup_self_v6().await.expect("should lookup");
+        assert_eq!(details.ip, "2001:4860:4860::8888");
+    }
+
+    #[tokio::test]
+    #[allow(clippy::bool_assert_comparison)]
     async fn lookup_single_ip() {
         let mut ipinfo = get_ipinfo_client();
 
@@ -351,6 +538,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[allow(clippy::bool_assert_comparison)]
     async fn lookup_single_ip_v6() {
         let mut ipinfo = get_ipinfo_client();
 
@@ -406,4 +594,263 @@ mod tests {
         assert_ne!(details.continent.code, "");
         assert_ne!(details.continent.name, "");
     }
+
+    #[tokio::test]
+    async fn export_import_cache() {
+        let mock_server = MockServer::start().await;
+
+        for (ip, asn) in [("8.8.8.8", "AS15169"), ("1.1.1.1", "AS13335")] {
+            Mock::given(method("GET"))
+                .and(path(format!("/{ip}")))
+                .respond_with(ResponseTemplate::new(200).set_body_json(
+                    serde_json::json!({
+                        "ip": ip,
+                        "asn": asn,
+                        "as_name": "Test",
+                        "as_domain": "example.com",
+                        "country_code": "US",
+                        "country": "United States",
+                        "continent_code": "NA",
+                        "continent": "North America"
+                    }),
+                ))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+
+        Mock::given(method("GET"))
+            .and(path("/me"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "ip": "4.2.2.4",
+                    "asn": "AS3356",
+                    "as_name": "Level 3",
+                    "as_domain": "lumen.com",
+                    "country_code": "US",
+                    "country": "United States"
+                }),
+            ))
+            .mount(&mock_server)
+            .await;
+
+        let config = || IpInfoLiteConfig {
+            token: Some("test_token".into()),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        };
+
+        let mut ipinfo = IpInfoLite::new(config()).expect("should construct");
+        ipinfo.lookup("8.8.8.8").await.expect("should lookup");
+        ipinfo.lookup("1.1.1.1").await.expect("should lookup");
+        ipinfo.lookup_self_v4().await.expect("should lookup");
+
+        let mut lines = Vec::new();
+        ipinfo
+            .export_cache()
+            .write_json_lines(&mut lines)
+            .expect("should write");
+        let snapshot =
+            CacheSnapshot::<IpDetailsLite>::read_json_lines(lines.as_slice())
+                .expect("should read");
+
+        // Least recently used first, without the client's own IP
+        let keys: Vec<_> =
+            snapshot.entries.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, ["8.8.8.8:1", "1.1.1.1:1"]);
+
+        // The new client is served from the imported cache
+        let mut ipinfo = IpInfoLite::new(config()).expect("should construct");
+        assert_eq!(ipinfo.import_cache(snapshot), 2);
+        let details = ipinfo.lookup("1.1.1.1").await.expect("should lookup");
+        assert_eq!(details.asn, "AS13335");
+        assert_eq!(details.country_name, "United States");
+        assert_eq!(details.continent.name, "North America");
+        assert!(!details.extra.contains_key("country_name"));
+    }
+
+    #[tokio::test]
+    async fn lookup_stale_while_revalidate() {
+        let mock_server = MockServer::start().await;
+
+        let response = |as_name: &str| {
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "ip": "8.8.8.8",
+                "asn": "AS15169",
+                "as_name": as_name,
+                "as_domain": "google.com",
+                "country_code": "US",
+                "country": "United States"
+            }))
+        };
+        Mock::given(method("GET"))
+            .and(path("/8.8.8.8"))
+            .respond_with(response("Google LLC"))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/8.8.8.8"))
+            .respond_with(response("Google"))
+            .mount(&mock_server)
+            .await;
+
+        let mut ipinfo = IpInfoLite::new(IpInfoLiteConfig {
+            token: Some("test_token".into()),
+            cache_ttl: Some(CacheTtl {
+                soft: Duration::ZERO,
+                hard: Duration::from_secs(60),
+            }),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        let details = ipinfo.lookup("8.8.8.8").await.expect("should lookup");
+        assert_eq!(details.as_name, "Google LLC");
+
+        // The stale result is returned while it is refreshed
+        let mut details =
+            ipinfo.lookup("8.8.8.8").await.expect("should lookup");
+        assert_eq!(details.as_name, "Google LLC");
+
+        // The refreshed result replaces it once the refresh completes
+        for _ in 0..100 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            details = ipinfo.lookup("8.8.8.8").await.expect("should lookup");
+            if details.as_name != "Google LLC" {
+                break;
+            }
+        }
+        assert_eq!(details.as_name, "Google");
+        assert_eq!(mock_server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn lookup_shared_cache() {
+        let plus_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/8.8.8.8"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "ip": "8.8.8.8",
+                    "geo": {
+                        "city": "Mountain View",
+                        "country": "United States",
+                        "country_code": "US",
+                        "latitude": 37.4056,
+                        "longitude": -122.0775
+                    },
+                    "as": {
+                        "asn": "AS15169",
+                        "name": "Google LLC",
+                        "domain": "google.com",
+                        "type": "hosting"
+                    },
+                    "is_anonymous": false,
+                    "is_anycast": true,
+                    "is_hosting": true,
+                    "is_mobile": false,
+                    "is_satellite": false
+                }),
+            ))
+            .expect(1)
+            .mount(&plus_server)
+            .await;
+
+        let cache = SharedCache::new(10).expect("should construct");
+        let mut plus = IpInfoPlus::builder()
+            .token("test_token")
+            .base_url(plus_server.uri())
+            .shared_cache(cache.clone())
+            .build()
+            .expect("should construct");
+        plus.lookup("8.8.8.8").await.expect("should lookup");
+
+        // Served from the Plus result without a request
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/8.8.8.8"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+        let mut ipinfo = IpInfoLite::builder()
+            .token("test_token")
+            .base_url(mock_server.uri())
+            .shared_cache(cache)
+            .build()
+            .expect("should construct");
+
+        let details = ipinfo.lookup("8.8.8.8").await.expect("should lookup");
+        assert_eq!(details.ip, "8.8.8.8");
+        assert_eq!(details.asn, "AS15169");
+        assert_eq!(details.as_name, "Google LLC");
+        assert_eq!(details.country_code, "US");
+        assert_eq!(details.continent.name, "North America");
+    }
+
+    #[tokio::test]
+    async fn lookup_self_shared_cache() {
+        let plus_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/me"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "ip": "8.8.8.8",
+                    "geo": {
+                        "city": "Mountain View",
+                        "country": "United States",
+                        "country_code": "US",
+                        "latitude": 37.4056,
+                        "longitude": -122.0775
+                    },
+                    "is_anonymous": false,
+                    "is_anycast": true,
+                    "is_hosting": true,
+                    "is_mobile": false,
+                    "is_satellite": false
+                }),
+            ))
+            .mount(&plus_server)
+            .await;
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/me"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "ip": "4.2.2.4",
+                    "asn": "AS3356",
+                    "as_name": "Level 3",
+                    "as_domain": "lumen.com",
+                    "country_code": "US",
+                    "country": "United States"
+                }),
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let cache = SharedCache::new(10).expect("should construct");
+        let mut plus = IpInfoPlus::builder()
+            .token("test_token")
+            .base_url(plus_server.uri())
+            .shared_cache(cache.clone())
+            .build()
+            .expect("should construct");
+        let mut ipinfo = IpInfoLite::builder()
+            .token("test_token")
+            .base_url(mock_server.uri())
+            .shared_cache(cache.clone())
+            .build()
+            .expect("should construct");
+
+        plus.lookup_self_v4().await.expect("should lookup");
+        assert!(cache.is_empty());
+
+        // The own address is looked up rather than taken from the Plus client
+        let details = ipinfo.lookup_self_v4().await.expect("should lookup");
+        assert_eq!(details.ip, "4.2.2.4");
+        assert_eq!(details.as_name, "Level 3");
+    }
 }
@@ -34,7 +34,7 @@
 //! #[tokio::main]
 //! async fn main() {
 //!   // Setup token and other configurations.
-//!   let config = IpInfoConfig { token: Some("my token".to_string()), ..Default::default() };
+//!   let config = IpInfoConfig { token: Some("my token".into()), ..Default::default() };
 //!
 //!   // Setup IpInfo structure and start looking up IP addresses.
 //!   let mut ipinfo = IpInfo::new(config).expect("should construct");
@@ -52,13 +52,37 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[macro_use]
 mod error;
+#[macro_use]
+mod trace;
 mod api;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 mod bogon;
+mod builder;
+mod cache;
+mod convert;
+mod coordinates;
+#[cfg(feature = "csv")]
+mod csvdb;
 mod data;
+mod enrich;
+mod hybrid;
 mod ipinfo;
 mod ipinfo_core;
 mod ipinfo_lite;
 mod ipinfo_plus;
+#[cfg(any(feature = "mmdb", feature = "csv"))]
+mod local;
+mod metrics;
+mod middleware;
+#[cfg(feature = "mmdb")]
+mod mmdb;
+mod retry;
+mod settings;
+mod shared;
+mod token;
+#[cfg(any(feature = "mmdb", feature = "csv"))]
+mod update;
 mod util;
 
 pub use crate::ipinfo::*;
@@ -67,6 +91,22 @@ pub use crate::ipinfo_lite::*;
 pub use crate::ipinfo_plus::*;
 pub use api::*;
 pub use bogon::*;
+pub use builder::*;
+pub use cache::{CacheEntry, CacheMode, CacheSnapshot, CacheTtl};
+pub use coordinates::Coordinates;
+#[cfg(feature = "csv")]
+pub use csvdb::{IpInfoCoreCsv, IpInfoLiteCsv};
 pub use data::*;
 pub use error::*;
+pub use hybrid::*;
+pub use metrics::*;
+pub use middleware::*;
+#[cfg(feature = "mmdb")]
+pub use mmdb::{IpInfoCoreDb, IpInfoLiteDb};
+pub use retry::RetryPolicy;
+pub use settings::{Settings, Tier, TierClient};
+pub use shared::SharedCache;
+pub use token::Token;
+#[cfg(any(feature = "mmdb", feature = "csv"))]
+pub use update::*;
 pub use util::*;
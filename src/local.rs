@@ -0,0 +1,99 @@
+//   Copyright 2019-2025 IPinfo library developers
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Details from local database records.
+
+use crate::{
+    enrich::CountryData, CoreAS, CoreGeo, IpDetailsCore, IpDetailsLite,
+};
+
+/// A record of a local database, keyed by IPinfo field names.
+pub(crate) trait Record {
+    /// Get a non-empty field as a string.
+    fn string(&self, key: &str) -> Option<String>;
+
+    /// Get a numeric field.
+    fn float(&self, key: &str) -> Option<f64>;
+
+    /// Get a boolean field, false if missing.
+    fn flag(&self, key: &str) -> bool;
+}
+
+/// Build Lite details from a record, enriched with the country data.
+pub(crate) fn lite_details(
+    ip: &str,
+    record: &impl Record,
+    country_data: &CountryData,
+) -> IpDetailsLite {
+    let mut details = IpDetailsLite {
+        ip: ip.to_string(),
+        country_code: record.string("country_code").unwrap_or_default(),
+        country: record.string("country").unwrap_or_default(),
+        asn: record.string("asn").unwrap_or_default(),
+        as_name: record.string("as_name").unwrap_or_default(),
+        as_domain: record.string("as_domain").unwrap_or_default(),
+        ..Default::default()
+    };
+    country_data.enrich(&mut details);
+    details
+}
+
+/// Build Core details from a record, enriched with the country data.
+pub(crate) fn core_details(
+    ip: &str,
+    record: &impl Record,
+    country_data: &CountryData,
+) -> IpDetailsCore {
+    let geo = CoreGeo {
+        city: record.string("city"),
+        region: record.string("region"),
+        region_code: record.string("region_code"),
+        country: record.string("country"),
+        country_code: record.string("country_code"),
+        continent: record.string("continent"),
+        continent_code: record.string("continent_code"),
+        latitude: record
+            .float("latitude")
+            .or_else(|| record.float("lat"))
+            .unwrap_or_default(),
+        longitude: record
+            .float("longitude")
+            .or_else(|| record.float("lng"))
+            .unwrap_or_default(),
+        timezone: record.string("timezone"),
+        postal_code: record.string("postal_code"),
+        ..Default::default()
+    };
+
+    let asn = record.string("asn").map(|asn| CoreAS {
+        asn,
+        name: record.string("as_name").unwrap_or_default(),
+        domain: record.string("as_domain").unwrap_or_default(),
+        as_type: record.string("as_type").unwrap_or_default(),
+    });
+
+    let mut details = IpDetailsCore {
+        ip: ip.to_string(),
+        geo: Some(geo),
+        asn,
+        is_anonymous: record.flag("is_anonymous"),
+        is_anycast: record.flag("is_anycast"),
+        is_hosting: record.flag("is_hosting"),
+        is_mobile: record.flag("is_mobile"),
+        is_satellite: record.flag("is_satellite"),
+        ..Default::default()
+    };
+    country_data.enrich(&mut details);
+    details
+}
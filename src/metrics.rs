@@ -0,0 +1,319 @@
+//   Copyright 2019-2025 IPinfo library developers
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! API usage and latency metrics.
+//!
+//! The clients report requests, cache lookups and batch sizes to a
+//! `MetricsSink`. With the `prometheus` feature enabled, `PrometheusMetrics`
+//! collects them and renders the Prometheus text exposition format.
+
+use std::{sync::Arc, time::Duration};
+
+/// Receives metrics from the clients.
+pub trait MetricsSink: Send + Sync {
+    /// Called for every API response with the endpoint, HTTP status and
+    /// latency.
+    fn record_request(
+        &self,
+        _endpoint: &str,
+        _status: u16,
+        _latency: Duration,
+    ) {
+    }
+
+    /// Called for every request that failed without a response.
+    fn record_error(&self, _endpoint: &str) {}
+
+    /// Called for every cache lookup.
+    fn record_cache(&self, _hit: bool) {}
+
+    /// Called for every batch request with the number of IPs in it.
+    fn record_batch(&self, _size: usize) {}
+}
+
+/// Record a cache lookup if a sink is configured.
+pub(crate) fn record_cache(metrics: &Option<Arc<dyn MetricsSink>>, hit: bool) {
+    if let Some(metrics) = metrics {
+        metrics.record_cache(hit);
+    }
+}
+
+#[cfg(feature = "prometheus")]
+pub use self::prometheus::PrometheusMetrics;
+
+#[cfg(feature = "prometheus")]
+mod prometheus {
+    use std::{
+        collections::BTreeMap, fmt::Write, sync::Mutex, time::Duration,
+    };
+
+    use super::MetricsSink;
+
+    const LATENCY_BUCKETS: [f64; 11] = [
+        0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+    ];
+    const BATCH_SIZE_BUCKETS: [f64; 7] =
+        [1.0, 10.0, 50.0, 100.0, 250.0, 500.0, 1000.0];
+
+    #[derive(Default)]
+    struct Histogram {
+        buckets: Vec<u64>,
+        sum: f64,
+        count: u64,
+    }
+
+    impl Histogram {
+        fn observe(&mut self, bounds: &[f64], value: f64) {
+            if self.buckets.is_empty() {
+                self.buckets = vec![0; bounds.len()];
+            }
+            for (bucket, bound) in self.buckets.iter_mut().zip(bounds) {
+                if value <= *bound {
+                    *bucket += 1;
+                }
+            }
+            self.sum += value;
+            self.count += 1;
+        }
+
+        fn render(
+            &self,
+            out: &mut String,
+            name: &str,
+            labels: &str,
+            bounds: &[f64],
+        ) {
+            let sep = if labels.is_empty() { "" } else { "," };
+            for (bucket, bound) in self.buckets.iter().zip(bounds) {
+                let _ = writeln!(
+                    out,
+                    "{name}_bucket{{{labels}{sep}le=\"{bound}\"}} {bucket}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{{labels}{sep}le=\"+Inf\"}} {}",
+                self.count
+            );
+            let braces = |s: &str| {
+                if s.is_empty() {
+                    String::new()
+                } else {
+                    format!("{{{s}}}")
+                }
+            };
+            let _ = writeln!(out, "{name}_sum{} {}", braces(labels), self.sum);
+            let _ =
+                writeln!(out, "{name}_count{} {}", braces(labels), self.count);
+        }
+    }
+
+    #[derive(Default)]
+    struct Metrics {
+        requests: BTreeMap<(String, u16), u64>,
+        errors: BTreeMap<String, u64>,
+        latency: BTreeMap<String, Histogram>,
+        cache_hits: u64,
+        cache_misses: u64,
+        batch_size: Histogram,
+    }
+
+    /// Metrics sink rendering the Prometheus text exposition format.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use ipinfo::{IpInfo, IpInfoConfig, PrometheusMetrics};
+    ///
+    /// let metrics = Arc::new(PrometheusMetrics::new());
+    /// let config = IpInfoConfig {
+    ///     metrics: Some(metrics.clone()),
+    ///     ..Default::default()
+    /// };
+    /// let ipinfo = IpInfo::new(config).expect("should construct");
+    ///
+    /// // Serve this from your metrics endpoint
+    /// let text = metrics.render();
+    /// ```
+    #[derive(Default)]
+    pub struct PrometheusMetrics {
+        metrics: Mutex<Metrics>,
+    }
+
+    impl PrometheusMetrics {
+        /// Construct a new, empty PrometheusMetrics structure.
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Render the collected metrics in the Prometheus text format.
+        pub fn render(&self) -> String {
+            let metrics = self.metrics.lock().unwrap();
+            let mut out = String::new();
+
+            out.push_str(
+                "# HELP ipinfo_requests_total Total number of API requests.\n",
+            );
+            out.push_str("# TYPE ipinfo_requests_total counter\n");
+            for ((endpoint, status), count) in &metrics.requests {
+                let _ = writeln!(
+                    out,
+                    "ipinfo_requests_total{{endpoint=\"{endpoint}\",status=\"{status}\"}} {count}"
+                );
+            }
+
+            out.push_str(
+                "# HELP ipinfo_request_errors_total Total number of API requests that failed without a response.\n",
+            );
+            out.push_str("# TYPE ipinfo_request_errors_total counter\n");
+            for (endpoint, count) in &metrics.errors {
+                let _ = writeln!(
+                    out,
+                    "ipinfo_request_errors_total{{endpoint=\"{endpoint}\"}} {count}"
+                );
+            }
+
+            out.push_str(
+                "# HELP ipinfo_request_duration_seconds API request latency.\n",
+            );
+            out.push_str("# TYPE ipinfo_request_duration_seconds histogram\n");
+            for (endpoint, histogram) in &metrics.latency {
+                histogram.render(
+                    &mut out,
+                    "ipinfo_request_duration_seconds",
+                    &format!("endpoint=\"{endpoint}\""),
+                    &LATENCY_BUCKETS,
+                );
+            }
+
+            out.push_str(
+                "# HELP ipinfo_cache_hits_total Total number of cache hits.\n",
+            );
+            out.push_str("# TYPE ipinfo_cache_hits_total counter\n");
+            let _ = writeln!(
+                out,
+                "ipinfo_cache_hits_total {}",
+                metrics.cache_hits
+            );
+
+            out.push_str(
+                "# HELP ipinfo_cache_misses_total Total number of cache misses.\n",
+            );
+            out.push_str("# TYPE ipinfo_cache_misses_total counter\n");
+            let _ = writeln!(
+                out,
+                "ipinfo_cache_misses_total {}",
+                metrics.cache_misses
+            );
+
+            out.push_str(
+                "# HELP ipinfo_batch_size Number of IPs per batch request.\n",
+            );
+            out.push_str("# TYPE ipinfo_batch_size histogram\n");
+            if metrics.batch_size.count > 0 {
+                metrics.batch_size.render(
+                    &mut out,
+                    "ipinfo_batch_size",
+                    "",
+                    &BATCH_SIZE_BUCKETS,
+                );
+            }
+
+            out
+        }
+    }
+
+    impl MetricsSink for PrometheusMetrics {
+        fn record_request(
+            &self,
+            endpoint: &str,
+            status: u16,
+            latency: Duration,
+        ) {
+            let mut metrics = self.metrics.lock().unwrap();
+            *metrics
+                .requests
+                .entry((endpoint.to_string(), status))
+                .or_default() += 1;
+            metrics
+                .latency
+                .entry(endpoint.to_string())
+                .or_default()
+                .observe(&LATENCY_BUCKETS, latency.as_secs_f64());
+        }
+
+        fn record_error(&self, endpoint: &str) {
+            let mut metrics = self.metrics.lock().unwrap();
+            *metrics.errors.entry(endpoint.to_string()).or_default() += 1;
+        }
+
+        fn record_cache(&self, hit: bool) {
+            let mut metrics = self.metrics.lock().unwrap();
+            if hit {
+                metrics.cache_hits += 1;
+            } else {
+                metrics.cache_misses += 1;
+            }
+        }
+
+        fn record_batch(&self, size: usize) {
+            let mut metrics = self.metrics.lock().unwrap();
+            metrics.batch_size.observe(&BATCH_SIZE_BUCKETS, size as f64);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn render_prometheus_text() {
+            let metrics = PrometheusMetrics::new();
+            metrics.record_request("lookup", 200, Duration::from_millis(20));
+            metrics.record_request("lookup", 200, Duration::from_millis(200));
+            metrics.record_request("batch", 429, Duration::from_millis(5));
+            metrics.record_error("lookup");
+            metrics.record_cache(true);
+            metrics.record_cache(false);
+            metrics.record_cache(false);
+            metrics.record_batch(2);
+
+            let text = metrics.render();
+
+            assert!(text.contains(
+                "ipinfo_requests_total{endpoint=\"lookup\",status=\"200\"} 2"
+            ));
+            assert!(text.contains(
+                "ipinfo_requests_total{endpoint=\"batch\",status=\"429\"} 1"
+            ));
+            assert!(text.contains(
+                "ipinfo_request_errors_total{endpoint=\"lookup\"} 1"
+            ));
+            assert!(text.contains(
+                "ipinfo_request_duration_seconds_bucket{endpoint=\"lookup\",le=\"0.025\"} 1"
+            ));
+            assert!(text.contains(
+                "ipinfo_request_duration_seconds_bucket{endpoint=\"lookup\",le=\"+Inf\"} 2"
+            ));
+            assert!(text.contains(
+                "ipinfo_request_duration_seconds_count{endpoint=\"lookup\"} 2"
+            ));
+            assert!(text.contains("ipinfo_cache_hits_total 1"));
+            assert!(text.contains("ipinfo_cache_misses_total 2"));
+            assert!(text.contains("ipinfo_batch_size_bucket{le=\"10\"} 1"));
+            assert!(text.contains("ipinfo_batch_size_sum 2"));
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//   Copyright 2019-2025 IPinfo library developers
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Request and response middleware.
+//!
+//! Middleware is shared by all clients and sees every request they send,
+//! which makes it the place for cross-cutting concerns such as tracing
+//! headers or auditing.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::Arc;
+//! use ipinfo::{IpInfo, IpInfoConfig, Middleware, ResponseInfo};
+//! use reqwest::header::HeaderValue;
+//!
+//! struct TraceId;
+//!
+//! impl Middleware for TraceId {
+//!     fn on_request(&self, request: &mut reqwest::Request) {
+//!         request
+//!             .headers_mut()
+//!             .insert("x-trace-id", HeaderValue::from_static("abc123"));
+//!     }
+//!
+//!     fn on_response(&self, response: &ResponseInfo) {
+//!         println!("{} {} in {:?}", response.status, response.url, response.latency);
+//!     }
+//! }
+//!
+//! let config = IpInfoConfig {
+//!     middleware: vec![Arc::new(TraceId)],
+//!     ..Default::default()
+//! };
+//! let ipinfo = IpInfo::new(config).expect("should construct");
+//! ```
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use reqwest::{Method, RequestBuilder, StatusCode, Url};
+
+use tokio::time::sleep;
+
+use crate::{IpError, MetricsSink, RetryPolicy};
+
+/// Details of a received API response.
+#[derive(Debug, Clone)]
+pub struct ResponseInfo {
+    /// The HTTP method of the request.
+    pub method: Method,
+
+    /// The URL of the request.
+    pub url: Url,
+
+    /// The HTTP status of the response.
+    pub status: StatusCode,
+
+    /// The time between sending the request and receiving the full body.
+    pub latency: Duration,
+
+    /// The size of the response body in bytes.
+    pub body_size: usize,
+}
+
+/// Hooks to modify outgoing requests and observe responses.
+pub trait Middleware: Send + Sync {
+    /// Called before a request is sent.
+    fn on_request(&self, _request: &mut reqwest::Request) {}
+
+    /// Called after a response has been received.
+    fn on_response(&self, _response: &ResponseInfo) {}
+}
+
+/// Send a request through the middleware, retrying it according to the
+/// retry policy, and return the response body.
+pub(crate) async fn send(
+    client: &reqwest::Client,
+    middleware: &[Arc<dyn Middleware>],
+    metrics: Option<&dyn MetricsSink>,
+    retry: &RetryPolicy,
+    endpoint: &str,
+    request: RequestBuilder,
+) -> Result<String, IpError> {
+    let mut request = request.build()?;
+    for m in middleware {
+        m.on_request(&mut request);
+    }
+
+    let mut retries = 0;
+    let (status, status_error, raw_resp) = loop {
+        // Keep a copy of the request in case it has to be retried
+        let next = match retries < retry.max_retries {
+            true => request.try_clone(),
+            false => None,
+        };
+
+        let outcome =
+            attempt(client, middleware, metrics, endpoint, request).await;
+        let retryable = match &outcome {
+            Ok((status, _, _)) => {
+                *status == StatusCode::TOO_MANY_REQUESTS
+                    || status.is_server_error()
+            }
+            Err(_) => true,
+        };
+        match next {
+            Some(next) if retryable => {
+                event!(
+                    warn,
+                    endpoint,
+                    retry = retries + 1,
+                    "retrying request"
+                );
+                sleep(retry.backoff(retries)).await;
+                retries += 1;
+                request = next;
+            }
+            _ => break outcome?,
+        }
+    };
+
+    // Check if we exhausted our request quota
+    if let StatusCode::TOO_MANY_REQUESTS = status {
+        event!(warn, "rate limit exceeded");
+        return Err(err!(RateLimitExceededError));
+    }
+
+    match status_error {
+        Some(e) => Err(e.into()),
+        None => Ok(raw_resp),
+    }
+}
+
+// Send a single request and read the response body
+async fn attempt(
+    client: &reqwest::Client,
+    middleware: &[Arc<dyn Middleware>],
+    metrics: Option<&dyn MetricsSink>,
+    endpoint: &str,
+    request: reqwest::Request,
+) -> Result<(StatusCode, Option<reqwest::Error>, String), reqwest::Error> {
+    let method = request.method().clone();
+    let url = request.url().clone();
+    let start = Instant::now();
+
+    // Acquire response
+    let response = async {
+        let response = client.execute(request).await?;
+        let status = response.status();
+        let status_error = response.error_for_status_ref().err();
+        let raw_resp = response.text().await?;
+        Ok((status, status_error, raw_resp))
+    }
+    .await;
+    let (status, status_error, raw_resp) = match response {
+        Ok(response) => response,
+        Err(e) => {
+            if let Some(metrics) = metrics {
+                metrics.record_error(endpoint);
+            }
+            return Err(e);
+        }
+    };
+
+    let info = ResponseInfo {
+        method,
+        url,
+        status,
+        latency: start.elapsed(),
+        body_size: raw_resp.len(),
+    };
+    event!(
+        debug,
+        method = %info.method,
+        url = %info.url,
+        status = info.status.as_u16(),
+        latency = ?info.latency,
+        "received response"
+    );
+    if let Some(metrics) = metrics {
+        metrics.record_request(endpoint, status.as_u16(), info.latency);
+    }
+    for m in middleware {
+        m.on_response(&info);
+    }
+
+    Ok((status, status_error, raw_resp))
+}
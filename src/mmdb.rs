@@ -0,0 +1,442 @@
+//   Copyright 2019-2025 IPinfo library developers
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Offline lookups in IPinfo MMDB databases.
+//!
+//! `IpInfoLiteDb` and `IpInfoCoreDb` read the IPinfo Lite and Core MMDB
+//! files and return the same details as `IpInfoLite` and `IpInfoCore`,
+//! including the static country enrichment, without calling the API.
+//!
+//! Requires the `mmdb` feature.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use ipinfo::IpInfoLiteDb;
+//!
+//! let db = IpInfoLiteDb::open("ipinfo_lite.mmdb").expect("should open");
+//! let details = db.lookup("8.8.8.8").expect("should lookup");
+//! println!("{}: {}", details.ip, details.country_name);
+//! ```
+
+use std::{net::IpAddr, path::Path};
+
+use maxminddb::{MaxMindDBError, Reader};
+use serde_json::{Map, Value};
+
+use crate::{
+    enrich::CountryData,
+    is_bogon,
+    local::{core_details, lite_details, Record},
+    IpDetailsCore, IpDetailsLite, IpError, IpInfoCoreConfig, IpInfoLiteConfig,
+};
+
+/// A record of an MMDB database.
+struct MmdbRecord(Map<String, Value>);
+
+impl Record for MmdbRecord {
+    fn string(&self, key: &str) -> Option<String> {
+        match self.0.get(key)? {
+            Value::String(s) if !s.is_empty() => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        }
+    }
+
+    fn float(&self, key: &str) -> Option<f64> {
+        match self.0.get(key)? {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        match self.0.get(key) {
+            Some(Value::Bool(b)) => *b,
+            Some(Value::String(s)) => s == "true",
+            Some(Value::Number(n)) => n.as_u64() == Some(1),
+            _ => false,
+        }
+    }
+}
+
+/// An MMDB database reader.
+struct Mmdb(Reader<Vec<u8>>);
+
+impl Mmdb {
+    fn open(path: impl AsRef<Path>) -> Result<Self, IpError> {
+        Ok(Self(Reader::open_readfile(path)?))
+    }
+
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self, IpError> {
+        Ok(Self(Reader::from_source(bytes)?))
+    }
+
+    fn record(&self, ip: &str) -> Result<MmdbRecord, IpError> {
+        let addr: IpAddr = ip.parse().map_err(|_| {
+            err!(ParseError, &format!("invalid IP address {ip}"))
+        })?;
+        match self.0.lookup::<Map<String, Value>>(addr) {
+            Ok(record) => Ok(MmdbRecord(record)),
+            Err(MaxMindDBError::AddressNotFoundError(_)) => Err(err!(
+                IpRequestError,
+                &format!("{ip} not found in database")
+            )),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Offline IPinfo Lite database.
+pub struct IpInfoLiteDb {
+    db: Mmdb,
+    country_data: CountryData,
+}
+
+impl IpInfoLiteDb {
+    /// Open an IPinfo Lite MMDB file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, IpError> {
+        Ok(Self {
+            db: Mmdb::open(path)?,
+            country_data: CountryData::default(),
+        })
+    }
+
+    /// Read an IPinfo Lite MMDB database from memory.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, IpError> {
+        Ok(Self {
+            db: Mmdb::from_bytes(bytes)?,
+            country_data: CountryData::default(),
+        })
+    }
+
+    /// Enrich details with the `default_*` country data of a client
+    /// configuration instead of the built-in tables.
+    pub fn with_country_data(mut self, config: &IpInfoLiteConfig) -> Self {
+        self.country_data = CountryData::from(config);
+        self
+    }
+
+    /// looks up IpDetailsLite for a single IP Address
+    pub fn lookup(&self, ip: &str) -> Result<IpDetailsLite, IpError> {
+        if is_bogon(ip) {
+            return Ok(IpDetailsLite {
+                ip: ip.to_string(),
+                bogon: Some(true),
+                ..Default::default()
+            });
+        }
+
+        let record = self.db.record(ip)?;
+        Ok(lite_details(ip, &record, &self.country_data))
+    }
+}
+
+/// Offline IPinfo Core database.
+pub struct IpInfoCoreDb {
+    db: Mmdb,
+    country_data: CountryData,
+}
+
+impl IpInfoCoreDb {
+    /// Open an IPinfo Core MMDB file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, IpError> {
+        Ok(Self {
+            db: Mmdb::open(path)?,
+            country_data: CountryData::default(),
+        })
+    }
+
+    /// Read an IPinfo Core MMDB database from memory.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, IpError> {
+        Ok(Self {
+            db: Mmdb::from_bytes(bytes)?,
+            country_data: CountryData::default(),
+        })
+    }
+
+    /// Enrich details with the `default_*` country data of a client
+    /// configuration instead of the built-in tables.
+    pub fn with_country_data(mut self, config: &IpInfoCoreConfig) -> Self {
+        self.country_data = CountryData::from(config);
+        self
+    }
+
+    /// looks up IpDetailsCore for a single IP Address
+    pub fn lookup(&self, ip: &str) -> Result<IpDetailsCore, IpError> {
+        if is_bogon(ip) {
+            return Ok(IpDetailsCore {
+                ip: ip.to_string(),
+                bogon: Some(true),
+                ..Default::default()
+            });
+        }
+
+        let record = self.db.record(ip)?;
+        Ok(core_details(ip, &record, &self.country_data))
+    }
+}
+
+/// Builds small MMDB databases for tests.
+#[cfg(test)]
+pub(crate) mod writer {
+    use std::net::IpAddr;
+
+    use serde_json::Value;
+
+    #[derive(Clone, Copy)]
+    enum Node {
+        Empty,
+        Child(usize),
+        Data(usize),
+    }
+
+    /// An in-memory IPv6 MMDB database with 24-bit records.
+    #[derive(Default)]
+    pub(crate) struct MmdbWriter {
+        nodes: Vec<[Node; 2]>,
+        data: Vec<u8>,
+    }
+
+    impl MmdbWriter {
+        /// Insert a network, e.g. "8.8.8.0/24", with a JSON object record.
+        pub(crate) fn insert(&mut self, network: &str, record: Value) {
+            let (addr, prefix) = network.split_once('/').unwrap();
+            let prefix: usize = prefix.parse().unwrap();
+            let (bits, prefix) = match addr.parse::<IpAddr>().unwrap() {
+                IpAddr::V4(v4) => (u32::from(v4) as u128, prefix + 96),
+                IpAddr::V6(v6) => (u128::from(v6), prefix),
+            };
+
+            let offset = self.data.len();
+            encode(&mut self.data, &record);
+
+            if self.nodes.is_empty() {
+                self.nodes.push([Node::Empty; 2]);
+            }
+            let mut node = 0;
+            for depth in 0..prefix {
+                let bit = ((bits >> (127 - depth)) & 1) as usize;
+                if depth == prefix - 1 {
+                    self.nodes[node][bit] = Node::Data(offset);
+                    break;
+                }
+                node = match self.nodes[node][bit] {
+                    Node::Child(child) => child,
+                    _ => {
+                        self.nodes.push([Node::Empty; 2]);
+                        let child = self.nodes.len() - 1;
+                        self.nodes[node][bit] = Node::Child(child);
+                        child
+                    }
+                };
+            }
+        }
+
+        /// Serialize the database.
+        pub(crate) fn build(&self) -> Vec<u8> {
+            let node_count = self.nodes.len();
+            let mut out = Vec::new();
+            for node in &self.nodes {
+                for record in node {
+                    let value = match *record {
+                        Node::Empty => node_count,
+                        Node::Child(child) => child,
+                        Node::Data(offset) => node_count + 16 + offset,
+                    };
+                    out.extend_from_slice(&(value as u32).to_be_bytes()[1..]);
+                }
+            }
+            out.extend_from_slice(&[0; 16]);
+            out.extend_from_slice(&self.data);
+
+            out.extend_from_slice(b"\xAB\xCD\xEFMaxMind.com");
+            encode(
+                &mut out,
+                &serde_json::json!({
+                    "binary_format_major_version": 2,
+                    "binary_format_minor_version": 0,
+                    "build_epoch": 0,
+                    "database_type": "ipinfo test",
+                    "description": {},
+                    "ip_version": 6,
+                    "languages": [],
+                    "node_count": node_count,
+                    "record_size": 24
+                }),
+            );
+            out
+        }
+    }
+
+    // Write a control byte for a data type and payload size
+    fn control(out: &mut Vec<u8>, kind: u8, size: usize) {
+        assert!(size < 29);
+        if kind > 7 {
+            out.push(size as u8);
+            out.push(kind - 7);
+        } else {
+            out.push((kind << 5) | size as u8);
+        }
+    }
+
+    fn encode(out: &mut Vec<u8>, value: &Value) {
+        match value {
+            Value::String(s) => {
+                control(out, 2, s.len());
+                out.extend_from_slice(s.as_bytes());
+            }
+            Value::Bool(b) => control(out, 14, *b as usize),
+            Value::Number(n) if n.is_u64() => {
+                let bytes = n.as_u64().unwrap().to_be_bytes();
+                let start = bytes.iter().take_while(|b| **b == 0).count();
+                control(out, 9, 8 - start);
+                out.extend_from_slice(&bytes[start..]);
+            }
+            Value::Number(n) => {
+                control(out, 3, 8);
+                out.extend_from_slice(&n.as_f64().unwrap().to_be_bytes());
+            }
+            Value::Array(items) => {
+                control(out, 11, items.len());
+                items.iter().for_each(|item| encode(out, item));
+            }
+            Value::Object(map) => {
+                control(out, 7, map.len());
+                for (key, value) in map {
+                    encode(out, &Value::String(key.clone()));
+                    encode(out, value);
+                }
+            }
+            Value::Null => panic!("MMDB records cannot hold null values"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::writer::MmdbWriter;
+    use super::*;
+    use crate::IpErrorKind;
+    use std::collections::HashMap;
+
+    fn database() -> Vec<u8> {
+        let mut writer = MmdbWriter::default();
+        writer.insert(
+            "8.8.8.0/24",
+            serde_json::json!({
+                "country_code": "US",
+                "country": "United States",
+                "continent_code": "NA",
+                "continent": "North America",
+                "asn": "AS15169",
+                "as_name": "Google LLC",
+                "as_domain": "google.com",
+                "city": "Mountain View",
+                "region": "California",
+                "latitude": "37.4056",
+                "longitude": -122.0775,
+                "timezone": "America/Los_Angeles",
+                "as_type": "hosting",
+                "is_anycast": true,
+                "is_hosting": "true"
+            }),
+        );
+        writer.insert(
+            "2001:4860::/32",
+            serde_json::json!({
+                "country_code": "DE",
+                "country": "Germany",
+                "asn": "AS15169"
+            }),
+        );
+        writer.build()
+    }
+
+    #[test]
+    fn lookup_lite_db() {
+        let db = IpInfoLiteDb::from_bytes(database()).expect("should open");
+
+        let details = db.lookup("8.8.8.8").expect("should lookup");
+        assert_eq!(details.ip, "8.8.8.8");
+        assert_eq!(details.country_code, "US");
+        assert_eq!(details.country_name, "United States");
+        assert_eq!(details.asn, "AS15169");
+        assert_eq!(details.as_domain, "google.com");
+        assert!(!details.is_eu);
+        assert_eq!(details.country_flag.emoji, "🇺🇸");
+        assert_eq!(details.country_currency.code, "USD");
+        assert_eq!(details.continent.code, "NA");
+
+        let details = db.lookup("2001:4860::8888").expect("should lookup");
+        assert_eq!(details.country_name, "Germany");
+        assert!(details.is_eu);
+
+        let details = db.lookup("10.0.0.1").expect("should lookup");
+        assert_eq!(details.bogon, Some(true));
+
+        assert_eq!(
+            db.lookup("1.1.1.1").err().unwrap().kind(),
+            IpErrorKind::IpRequestError
+        );
+        assert_eq!(
+            db.lookup("not an ip").err().unwrap().kind(),
+            IpErrorKind::ParseError
+        );
+
+        // Client country data replaces the built-in tables
+        let config = IpInfoLiteConfig {
+            default_countries: Some(HashMap::from([(
+                "DE".to_string(),
+                "Deutschland".to_string(),
+            )])),
+            default_eu: Some(Vec::new()),
+            ..Default::default()
+        };
+        let db = IpInfoLiteDb::from_bytes(database())
+            .expect("should open")
+            .with_country_data(&config);
+        let details = db.lookup("2001:4860::8888").expect("should lookup");
+        assert_eq!(details.country_name, "Deutschland");
+        assert!(!details.is_eu);
+    }
+
+    #[test]
+    fn lookup_core_db() {
+        let db = IpInfoCoreDb::from_bytes(database()).expect("should open");
+
+        let details = db.lookup("8.8.8.8").expect("should lookup");
+        let geo = details.geo.expect("should have geo");
+        assert_eq!(geo.city.as_deref(), Some("Mountain View"));
+        assert_eq!(geo.latitude, 37.4056);
+        assert_eq!(geo.longitude, -122.0775);
+        assert_eq!(geo.country_name.as_deref(), Some("United States"));
+        assert_eq!(geo.continent_info.unwrap().code, "NA");
+        let asn = details.asn.expect("should have asn");
+        assert_eq!(asn.name, "Google LLC");
+        assert_eq!(asn.as_type, "hosting");
+        assert!(details.is_anycast);
+        assert!(details.is_hosting);
+        assert!(!details.is_mobile);
+
+        assert_eq!(
+            IpInfoCoreDb::from_bytes(b"not a database".to_vec())
+                .err()
+                .unwrap()
+                .kind(),
+            IpErrorKind::DatabaseError
+        );
+    }
+}
@@ -0,0 +1,562 @@
+//   Copyright 2019-2025 IPinfo library developers
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Client configuration from the environment and configuration files.
+//!
+//! `Settings` holds the deployment-specific part of a client configuration
+//! and can be read from environment variables, JSON or, with the `toml`
+//! feature, TOML. It converts into the configuration of any client, or into
+//! the client of its tier with `Settings::into_client`.
+//!
+//! # Example
+//!
+//! ```
+//! use ipinfo::{IpInfo, IpInfoConfig, Settings, Tier, TierClient};
+//!
+//! let settings = Settings::from_json(
+//!     r#"{
+//!         "token": "my token",
+//!         "tier": "standard",
+//!         "timeout_ms": 5000,
+//!         "cache_size": 1000,
+//!         "retry": { "max_retries": 3 }
+//!     }"#,
+//! )
+//! .expect("should parse");
+//! assert_eq!(settings.tier, Tier::Standard);
+//!
+//! let ipinfo = IpInfo::new(settings.clone().into()).expect("should construct");
+//!
+//! // Or the client of the configured tier
+//! let client = settings.into_client().expect("should construct");
+//! assert!(matches!(client, TierClient::Standard(_)));
+//! ```
+
+use std::{env, fs, path::Path, str::FromStr, time::Duration};
+
+use serde::Deserialize;
+
+use crate::{
+    CacheMode, IpDetailsCommon, IpError, IpInfo, IpInfoConfig, IpInfoCore,
+    IpInfoCoreConfig, IpInfoLite, IpInfoLiteConfig, IpInfoPlus,
+    IpInfoPlusConfig, RetryPolicy, Token,
+};
+
+/// IPinfo API tier.
+///
+/// `Settings::into_client` constructs the client of the configured tier.
+/// Converting `Settings` into a client configuration does not look at it.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tier {
+    /// The standard API, used by `IpInfo`.
+    #[default]
+    Standard,
+
+    /// The Lite API, used by `IpInfoLite`.
+    Lite,
+
+    /// The Core API, used by `IpInfoCore`.
+    Core,
+
+    /// The Plus API, used by `IpInfoPlus`.
+    Plus,
+}
+
+impl FromStr for Tier {
+    type Err = IpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "standard" => Ok(Tier::Standard),
+            "lite" => Ok(Tier::Lite),
+            "core" => Ok(Tier::Core),
+            "plus" => Ok(Tier::Plus),
+            _ => Err(err!(ConfigError, &format!("unknown tier {s}"))),
+        }
+    }
+}
+
+/// Deployment settings for a client.
+///
+/// Unset values keep the client defaults.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// IPinfo access token.
+    pub token: Option<Token>,
+
+    /// The API tier of the client built by `Settings::into_client`.
+    pub tier: Tier,
+
+    /// The timeout of HTTP requests, in milliseconds in files.
+    #[serde(rename = "timeout_ms", with = "millis::option")]
+    pub timeout: Option<Duration>,
+
+    /// The size of the LRU cache.
+    pub cache_size: Option<usize>,
+
+    /// How lookup results are cached.
+    pub cache_mode: Option<CacheMode>,
+
+    /// Retry policy for failed requests.
+    pub retry: Option<RetryPolicy>,
+
+    /// Base URL for API requests.
+    pub base_url: Option<String>,
+
+    /// Base URL for API requests over IPv6.
+    pub base_url_v6: Option<String>,
+}
+
+impl Settings {
+    /// Read the settings from environment variables.
+    ///
+    /// | Variable | Setting |
+    /// |----------|---------|
+    /// | `IPINFO_TOKEN` | `token` |
+    /// | `IPINFO_TIER` | `tier` |
+    /// | `IPINFO_TIMEOUT_MS` | `timeout` |
+    /// | `IPINFO_CACHE_SIZE` | `cache_size` |
+    /// | `IPINFO_CACHE_MODE` | `cache_mode` |
+    /// | `IPINFO_MAX_RETRIES` | `retry.max_retries` |
+    /// | `IPINFO_RETRY_INITIAL_BACKOFF_MS` | `retry.initial_backoff` |
+    /// | `IPINFO_RETRY_MAX_BACKOFF_MS` | `retry.max_backoff` |
+    /// | `IPINFO_BASE_URL` | `base_url` |
+    /// | `IPINFO_BASE_URL_V6` | `base_url_v6` |
+    pub fn from_env() -> Result<Self, IpError> {
+        Self::from_vars(|key| env::var(key).ok())
+    }
+
+    // Read the settings from variables, with `var` returning their values
+    fn from_vars(
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, IpError> {
+        // Empty values are treated as unset
+        let var = |key: &str| var(key).filter(|value| !value.is_empty());
+        let mut settings = Settings {
+            token: var("IPINFO_TOKEN").map(Token::from),
+            tier: parse_var(var, "IPINFO_TIER")?.unwrap_or_default(),
+            timeout: parse_var(var, "IPINFO_TIMEOUT_MS")?
+                .map(Duration::from_millis),
+            cache_size: parse_var(var, "IPINFO_CACHE_SIZE")?,
+            cache_mode: parse_var(var, "IPINFO_CACHE_MODE")?,
+            retry: None,
+            base_url: var("IPINFO_BASE_URL"),
+            base_url_v6: var("IPINFO_BASE_URL_V6"),
+        };
+
+        let max_retries = parse_var(var, "IPINFO_MAX_RETRIES")?;
+        let initial_backoff =
+            parse_var(var, "IPINFO_RETRY_INITIAL_BACKOFF_MS")?;
+        let max_backoff = parse_var(var, "IPINFO_RETRY_MAX_BACKOFF_MS")?;
+        if max_retries.is_some()
+            || initial_backoff.is_some()
+            || max_backoff.is_some()
+        {
+            let default = RetryPolicy::default();
+            settings.retry = Some(RetryPolicy {
+                max_retries: max_retries.unwrap_or(default.max_retries),
+                initial_backoff: initial_backoff
+                    .map(Duration::from_millis)
+                    .unwrap_or(default.initial_backoff),
+                max_backoff: max_backoff
+                    .map(Duration::from_millis)
+                    .unwrap_or(default.max_backoff),
+            });
+        }
+
+        Ok(settings)
+    }
+
+    /// Construct the client of the configured tier.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ipinfo::Settings;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let settings = Settings::from_env().expect("should read");
+    ///     let mut client = settings.into_client().expect("should construct");
+    ///     let details = client.lookup("8.8.8.8").await.expect("should lookup");
+    ///     println!("{:?}", details.country_code);
+    /// }
+    /// ```
+    pub fn into_client(self) -> Result<TierClient, IpError> {
+        Ok(match self.tier {
+            Tier::Standard => {
+                TierClient::Standard(Box::new(IpInfo::new(self.into())?))
+            }
+            Tier::Lite => TierClient::Lite(IpInfoLite::new(self.into())?),
+            Tier::Core => TierClient::Core(IpInfoCore::new(self.into())?),
+            Tier::Plus => TierClient::Plus(IpInfoPlus::new(self.into())?),
+        })
+    }
+
+    /// Parse the settings from JSON.
+    pub fn from_json(s: &str) -> Result<Self, IpError> {
+        serde_json::from_str(s).map_err(|e| err!(ConfigError, &e.to_string()))
+    }
+
+    /// Parse the settings from TOML.
+    ///
+    /// Requires the `toml` feature.
+    #[cfg(feature = "toml")]
+    pub fn from_toml(s: &str) -> Result<Self, IpError> {
+        toml::from_str(s).map_err(|e| err!(ConfigError, &e.to_string()))
+    }
+
+    /// Read the settings from a `.json` or, with the `toml` feature, a
+    /// `.toml` file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, IpError> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|e| {
+            err!(ConfigError, &format!("{}: {e}", path.display()))
+        })?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json(&contents),
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml(&contents),
+            _ => Err(err!(
+                ConfigError,
+                &format!("unsupported settings file {}", path.display())
+            )),
+        }
+    }
+}
+
+// Read and parse a variable
+fn parse_var<T>(
+    var: impl Fn(&str) -> Option<String>,
+    key: &str,
+) -> Result<Option<T>, IpError>
+where
+    T: FromStr,
+    T::Err: ToString,
+{
+    var(key)
+        .map(|value| {
+            value.parse().map_err(|e: T::Err| {
+                err!(ConfigError, &format!("{key}: {}", e.to_string()))
+            })
+        })
+        .transpose()
+}
+
+/// A client of the tier chosen in `Settings`.
+///
+/// Lookups return the details all tiers have in common; match on the
+/// client for the full details of its tier.
+pub enum TierClient {
+    /// A client of the standard API.
+    Standard(Box<IpInfo>),
+
+    /// A client of the Lite API.
+    Lite(IpInfoLite),
+
+    /// A client of the Core API.
+    Core(IpInfoCore),
+
+    /// A client of the Plus API.
+    Plus(IpInfoPlus),
+}
+
+impl TierClient {
+    /// The tier of the client.
+    pub fn tier(&self) -> Tier {
+        match self {
+            TierClient::Standard(_) => Tier::Standard,
+            TierClient::Lite(_) => Tier::Lite,
+            TierClient::Core(_) => Tier::Core,
+            TierClient::Plus(_) => Tier::Plus,
+        }
+    }
+
+    /// looks up the details of a single IP Address.
+    pub async fn lookup(
+        &mut self,
+        ip: &str,
+    ) -> Result<IpDetailsCommon, IpError> {
+        Ok(match self {
+            TierClient::Standard(client) => (&client.lookup(ip).await?).into(),
+            TierClient::Lite(client) => (&client.lookup(ip).await?).into(),
+            TierClient::Core(client) => (&client.lookup(ip).await?).into(),
+            TierClient::Plus(client) => (&client.lookup(ip).await?).into(),
+        })
+    }
+
+    /// looks up the details of your own v4 IP.
+    pub async fn lookup_self_v4(
+        &mut self,
+    ) -> Result<IpDetailsCommon, IpError> {
+        Ok(match self {
+            TierClient::Standard(client) => {
+                (&client.lookup_self_v4().await?).into()
+            }
+            TierClient::Lite(client) => {
+                (&client.lookup_self_v4().await?).into()
+            }
+            TierClient::Core(client) => {
+                (&client.lookup_self_v4().await?).into()
+            }
+            TierClient::Plus(client) => {
+                (&client.lookup_self_v4().await?).into()
+            }
+        })
+    }
+
+    /// looks up the details of your own v6 IP.
+    pub async fn lookup_self_v6(
+        &mut self,
+    ) -> Result<IpDetailsCommon, IpError> {
+        Ok(match self {
+            TierClient::Standard(client) => {
+                (&client.lookup_self_v6().await?).into()
+            }
+            TierClient::Lite(client) => {
+                (&client.lookup_self_v6().await?).into()
+            }
+            TierClient::Core(client) => {
+                (&client.lookup_self_v6().await?).into()
+            }
+            TierClient::Plus(client) => {
+                (&client.lookup_self_v6().await?).into()
+            }
+        })
+    }
+}
+
+/// Implement the conversion from `Settings` for a client configuration
+macro_rules! from_settings {
+    ($config:ident) => {
+        impl From<Settings> for $config {
+            fn from(settings: Settings) -> Self {
+                let default = Self::default();
+                Self {
+                    token: settings.token,
+                    timeout: settings.timeout.unwrap_or(default.timeout),
+                    cache_size: settings
+                        .cache_size
+                        .unwrap_or(default.cache_size),
+                    cache_mode: settings
+                        .cache_mode
+                        .unwrap_or(default.cache_mode),
+                    retry: settings.retry.unwrap_or(default.retry),
+                    base_url: settings.base_url,
+                    base_url_v6: settings.base_url_v6,
+                    ..default
+                }
+            }
+        }
+
+        impl $config {
+            /// Read the configuration from environment variables, see
+            /// `Settings::from_env`.
+            pub fn from_env() -> Result<Self, IpError> {
+                Settings::from_env().map(Self::from)
+            }
+        }
+    };
+}
+
+from_settings!(IpInfoConfig);
+from_settings!(IpInfoLiteConfig);
+from_settings!(IpInfoCoreConfig);
+from_settings!(IpInfoPlusConfig);
+
+/// Deserialize durations from integer milliseconds.
+pub(crate) mod millis {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer};
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+
+    pub mod option {
+        use super::*;
+
+        pub fn deserialize<'de, D>(
+            deserializer: D,
+        ) -> Result<Option<Duration>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Option::<u64>::deserialize(deserializer)
+                .map(|ms| ms.map(Duration::from_millis))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IpErrorKind;
+    use std::collections::HashMap;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn settings_from_json() {
+        let settings = Settings::from_json(
+            r#"{
+                "token": "test_token",
+                "tier": "lite",
+                "timeout_ms": 1500,
+                "cache_mode": "prefix",
+                "retry": { "max_retries": 2, "max_backoff_ms": 1000 },
+                "base_url": "http://127.0.0.1:8080/lite"
+            }"#,
+        )
+        .expect("should parse");
+        assert_eq!(settings.tier, Tier::Lite);
+
+        let config = IpInfoLiteConfig::from(settings);
+        assert_eq!(config.token.unwrap().expose_secret(), "test_token");
+        assert_eq!(config.timeout, Duration::from_millis(1500));
+        assert_eq!(config.cache_size, 100);
+        assert_eq!(config.cache_mode, CacheMode::Prefix);
+        assert_eq!(config.retry.max_retries, 2);
+        assert_eq!(config.retry.initial_backoff, Duration::from_millis(100));
+        assert_eq!(config.retry.max_backoff, Duration::from_secs(1));
+        assert_eq!(
+            config.base_url.as_deref(),
+            Some("http://127.0.0.1:8080/lite")
+        );
+
+        assert_eq!(
+            Settings::from_json(r#"{"cache_sise": 10}"#)
+                .err()
+                .unwrap()
+                .kind(),
+            IpErrorKind::ConfigError
+        );
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn settings_from_toml() {
+        let settings = Settings::from_toml(
+            r#"
+            tier = "plus"
+            cache_size = 500
+
+            [retry]
+            max_retries = 3
+            initial_backoff_ms = 50
+            "#,
+        )
+        .expect("should parse");
+
+        assert_eq!(settings.tier, Tier::Plus);
+        assert_eq!(settings.cache_size, Some(500));
+        let retry = settings.retry.unwrap();
+        assert_eq!(retry.max_retries, 3);
+        assert_eq!(retry.initial_backoff, Duration::from_millis(50));
+    }
+
+    #[test]
+    fn settings_from_env() {
+        let mut vars = HashMap::from([
+            ("IPINFO_TIER", "core"),
+            ("IPINFO_TIMEOUT_MS", "2500"),
+            ("IPINFO_CACHE_SIZE", ""),
+            ("IPINFO_CACHE_MODE", "prefix"),
+            ("IPINFO_MAX_RETRIES", "4"),
+            ("IPINFO_BASE_URL", "http://127.0.0.1:8080/core"),
+        ]);
+        let from_vars = |vars: &HashMap<&str, &str>| {
+            Settings::from_vars(|key| vars.get(key).map(|v| v.to_string()))
+        };
+
+        let settings = from_vars(&vars).expect("should read");
+        assert_eq!(settings.tier, Tier::Core);
+        assert_eq!(settings.timeout, Some(Duration::from_millis(2500)));
+        assert_eq!(settings.cache_size, None);
+        assert_eq!(settings.cache_mode, Some(CacheMode::Prefix));
+        let retry = settings.retry.clone().expect("should have retry");
+        assert_eq!(retry.max_retries, 4);
+        assert_eq!(
+            retry.initial_backoff,
+            RetryPolicy::default().initial_backoff
+        );
+
+        let config = IpInfoCoreConfig::from(settings.clone());
+        assert_eq!(config.timeout, Duration::from_millis(2500));
+        assert_eq!(config.cache_size, 100);
+        assert_eq!(
+            config.base_url.as_deref(),
+            Some("http://127.0.0.1:8080/core")
+        );
+
+        // The tier picks the client
+        let client = settings.into_client().expect("should construct");
+        assert_eq!(client.tier(), Tier::Core);
+        assert!(matches!(client, TierClient::Core(_)));
+
+        vars.insert("IPINFO_TIMEOUT_MS", "soon");
+        let err = from_vars(&vars).err().unwrap();
+        assert_eq!(err.kind(), IpErrorKind::ConfigError);
+    }
+
+    #[tokio::test]
+    async fn tier_client_lookup() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/8.8.8.8"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "ip": "8.8.8.8",
+                    "asn": "AS15169",
+                    "as_name": "Google LLC",
+                    "as_domain": "google.com",
+                    "country_code": "US",
+                    "country": "United States"
+                }),
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let settings = Settings {
+            token: Some("test_token".into()),
+            tier: Tier::Lite,
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        };
+        let mut client = settings.into_client().expect("should construct");
+        assert_eq!(client.tier(), Tier::Lite);
+
+        let details = client.lookup("8.8.8.8").await.expect("should lookup");
+        assert_eq!(details.country_code.as_deref(), Some("US"));
+        assert_eq!(details.as_name.as_deref(), Some("Google LLC"));
+        assert_eq!(details.city, None);
+    }
+
+    #[test]
+    fn tier_from_str() {
+        assert_eq!("Core".parse::<Tier>().unwrap(), Tier::Core);
+        assert_eq!(
+            "gold".parse::<Tier>().err().unwrap().kind(),
+            IpErrorKind::ConfigError
+        );
+    }
+}
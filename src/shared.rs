@@ -0,0 +1,317 @@
+//   Copyright 2019-2025 IPinfo library developers
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! A cache shared by the clients of different API tiers.
+//!
+//! # Example
+//!
+//! ```
+//! use ipinfo::{IpInfoCore, IpInfoLite, IpInfoPlus, SharedCache};
+//!
+//! let cache = SharedCache::new(10_000).expect("should construct");
+//! let plus = IpInfoPlus::builder()
+//!     .shared_cache(cache.clone())
+//!     .build()
+//!     .expect("should construct");
+//! // Lookups already made by `plus` are served without a request
+//! let lite = IpInfoLite::builder()
+//!     .shared_cache(cache)
+//!     .build()
+//!     .expect("should construct");
+//! ```
+
+use std::{
+    net::IpAddr,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use lru::LruCache;
+
+use crate::{
+    cache::{Cacheable, Cached, DetailsCache},
+    cache_key,
+    enrich::{CountryData, Enrich},
+    IpDetailsCore, IpDetailsLite, IpDetailsPlus, IpError,
+};
+
+/// Lookup details of any tier.
+#[derive(Clone)]
+pub(crate) enum TierDetails {
+    Lite(Box<IpDetailsLite>),
+    Core(Box<IpDetailsCore>),
+    Plus(Box<IpDetailsPlus>),
+}
+
+impl TierDetails {
+    // How much detail the tier has
+    fn rank(&self) -> usize {
+        match self {
+            TierDetails::Lite(_) => 0,
+            TierDetails::Core(_) => 1,
+            TierDetails::Plus(_) => 2,
+        }
+    }
+}
+
+/// The latest details of an address from each tier, and when they were
+/// fetched, indexed by rank.
+type TierEntries = [Option<(SystemTime, TierDetails)>; 3];
+
+/// Lookup details of a tier which can be shared.
+pub(crate) trait TierCacheable: Sized {
+    /// Project the details of this or a richer tier down to this tier.
+    fn project(details: &TierDetails) -> Option<Self>;
+
+    fn into_tier(self) -> TierDetails;
+
+    /// The IP address the details are for.
+    fn ip_mut(&mut self) -> &mut String;
+}
+
+impl TierCacheable for IpDetailsLite {
+    fn project(details: &TierDetails) -> Option<Self> {
+        Some(match details {
+            TierDetails::Lite(details) => *details.clone(),
+            TierDetails::Core(details) => (**details).clone().into(),
+            TierDetails::Plus(details) => (**details).clone().into(),
+        })
+    }
+
+    fn into_tier(self) -> TierDetails {
+        TierDetails::Lite(Box::new(self))
+    }
+
+    fn ip_mut(&mut self) -> &mut String {
+        &mut self.ip
+    }
+}
+
+impl TierCacheable for IpDetailsCore {
+    fn project(details: &TierDetails) -> Option<Self> {
+        match details {
+            TierDetails::Lite(_) => None,
+            TierDetails::Core(details) => Some(*details.clone()),
+            TierDetails::Plus(details) => Some((**details).clone().into()),
+        }
+    }
+
+    fn into_tier(self) -> TierDetails {
+        TierDetails::Core(Box::new(self))
+    }
+
+    fn ip_mut(&mut self) -> &mut String {
+        &mut self.ip
+    }
+}
+
+impl TierCacheable for IpDetailsPlus {
+    fn project(details: &TierDetails) -> Option<Self> {
+        match details {
+            TierDetails::Plus(details) => Some(*details.clone()),
+            _ => None,
+        }
+    }
+
+    fn into_tier(self) -> TierDetails {
+        TierDetails::Plus(Box::new(self))
+    }
+
+    fn ip_mut(&mut self) -> &mut String {
+        &mut self.ip
+    }
+}
+
+/// An LRU cache shared by Lite, Core and Plus clients.
+///
+/// The cache keeps the latest result of each tier for each IP address, and
+/// serves lookups from the most recently fetched result of the same or a
+/// richer tier: Plus results answer Core and Lite lookups, and Core results
+/// answer Lite lookups. Lookups of a client's own address are not shared.
+/// Clones share the same cache.
+#[derive(Clone)]
+pub struct SharedCache {
+    entries: Arc<Mutex<LruCache<String, TierEntries>>>,
+}
+
+impl SharedCache {
+    /// Create a shared cache holding up to `capacity` IP addresses.
+    pub fn new(capacity: usize) -> Result<Self, IpError> {
+        let capacity = NonZeroUsize::new(capacity).ok_or_else(|| {
+            err!(ConfigError, "cache size must be greater than zero")
+        })?;
+        Ok(Self {
+            entries: Arc::new(Mutex::new(LruCache::new(capacity))),
+        })
+    }
+
+    /// The number of cached IP addresses.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the most recently fetched details of an address which answer the
+    /// requested tier, projected to it, and when they were fetched.
+    pub(crate) fn get<V: TierCacheable>(
+        &self,
+        ip: &str,
+    ) -> Option<(SystemTime, V)> {
+        let mut entries = self.entries.lock().unwrap();
+        entries
+            .get(&cache_key(ip))?
+            .iter()
+            .flatten()
+            .filter_map(|(fetched_at, details)| {
+                Some((*fetched_at, V::project(details)?))
+            })
+            .max_by_key(|(fetched_at, _)| *fetched_at)
+    }
+
+    /// Cache the details of an address, replacing earlier details of the
+    /// same tier.
+    pub(crate) fn put<V: TierCacheable>(&self, ip: &str, details: V) {
+        let details = details.into_tier();
+        let rank = details.rank();
+        let mut entries = self.entries.lock().unwrap();
+        let tiers = entries.get_or_insert_mut(cache_key(ip), Default::default);
+        tiers[rank] = Some((SystemTime::now(), details));
+    }
+}
+
+// The shared cache, unless the lookup is of the client's own address, which
+// is keyed by "me" and differs between hosts and address families
+fn shared_for<'a>(
+    shared_cache: Option<&'a SharedCache>,
+    ip: &str,
+) -> Option<&'a SharedCache> {
+    shared_cache.filter(|_| ip.parse::<IpAddr>().is_ok())
+}
+
+/// Look up details cached by a client of the same or a richer tier, and
+/// keep them in the client's own cache with their original fetch time.
+pub(crate) fn shared_lookup<V>(
+    shared_cache: Option<&SharedCache>,
+    cache: &mut DetailsCache<V>,
+    country_data: &CountryData,
+    ip: &str,
+) -> Option<Cached<V>>
+where
+    V: Cacheable + TierCacheable + Enrich,
+{
+    let (fetched_at, mut details) =
+        shared_for(shared_cache, ip)?.get::<V>(ip)?;
+    *details.ip_mut() = ip.to_string();
+    country_data.enrich(&mut details);
+    cache.put_at(ip, details, fetched_at);
+    cache.get(ip)
+}
+
+/// Cache the details of an address in the client's own cache, and in the
+/// shared cache for clients of other tiers.
+pub(crate) fn cache_details<V>(
+    shared_cache: Option<&SharedCache>,
+    cache: &mut DetailsCache<V>,
+    ip: &str,
+    details: V,
+) where
+    V: Cacheable + TierCacheable,
+{
+    if let Some(shared_cache) = shared_for(shared_cache, ip) {
+        shared_cache.put(ip, details.clone());
+    }
+    cache.put(ip, details);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PlusAS, PlusGeo};
+    use std::time::Duration;
+
+    fn plus_details() -> IpDetailsPlus {
+        IpDetailsPlus {
+            ip: "8.8.8.8".to_string(),
+            geo: Some(PlusGeo {
+                city: Some("Mountain View".to_string()),
+                country: Some("United States".to_string()),
+                country_code: Some("US".to_string()),
+                continent: Some("North America".to_string()),
+                continent_code: Some("NA".to_string()),
+                latitude: 37.4056,
+                longitude: -122.0775,
+                ..Default::default()
+            }),
+            asn: Some(PlusAS {
+                asn: "AS15169".to_string(),
+                name: "Google LLC".to_string(),
+                domain: "google.com".to_string(),
+                as_type: "hosting".to_string(),
+                last_changed: None,
+            }),
+            is_anycast: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn shared_cache_projects_down() {
+        let cache = SharedCache::new(10).expect("should construct");
+        cache.put("8.8.8.8", plus_details());
+
+        let (_, core) = cache.get::<IpDetailsCore>("8.8.8.8").unwrap();
+        let geo = core.geo.expect("should have geo");
+        assert_eq!(geo.city.as_deref(), Some("Mountain View"));
+        assert_eq!(geo.latitude, 37.4056);
+        assert!(core.is_anycast);
+        assert_eq!(core.asn.unwrap().as_type, "hosting");
+
+        let (_, lite) = cache.get::<IpDetailsLite>("8.8.8.8").unwrap();
+        assert_eq!(lite.country_code, "US");
+        assert_eq!(lite.country, "United States");
+        assert_eq!(lite.as_name, "Google LLC");
+        assert_eq!(lite.continent.code, "NA");
+
+        // Poorer results do not replace richer ones, but answer their own
+        // tier once they are fresher
+        std::thread::sleep(Duration::from_millis(1));
+        cache.put(
+            "8.8.8.8",
+            IpDetailsLite {
+                as_name: "Google".to_string(),
+                ..lite
+            },
+        );
+        let (lite_at, lite) = cache.get::<IpDetailsLite>("8.8.8.8").unwrap();
+        assert_eq!(lite.as_name, "Google");
+        let (plus_at, plus) = cache.get::<IpDetailsPlus>("8.8.8.8").unwrap();
+        assert_eq!(plus.asn.unwrap().name, "Google LLC");
+        assert!(lite_at > plus_at);
+        let (_, core) = cache.get::<IpDetailsCore>("8.8.8.8").unwrap();
+        assert_eq!(core.asn.unwrap().name, "Google LLC");
+
+        // Richer tiers are not answered from poorer results
+        cache.put("1.1.1.1", IpDetailsLite::default());
+        assert!(cache.get::<IpDetailsCore>("1.1.1.1").is_none());
+        assert!(cache.get::<IpDetailsLite>("1.1.1.1").is_some());
+        assert_eq!(cache.len(), 2);
+
+        assert!(SharedCache::new(0).is_err());
+    }
+}
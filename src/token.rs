@@ -0,0 +1,177 @@
+//   Copyright 2019-2025 IPinfo library developers
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! IPinfo access token.
+
+use std::{env, fmt, fs, path::Path};
+
+use reqwest::RequestBuilder;
+use serde::{Deserialize, Deserializer};
+use zeroize::Zeroizing;
+
+use crate::IpError;
+
+/// An IPinfo access token.
+///
+/// The token is redacted from `Debug` and `Display` output and its memory is
+/// zeroed on drop.
+///
+/// # Example
+///
+/// ```
+/// use ipinfo::{IpInfoConfig, Token};
+///
+/// let config = IpInfoConfig {
+///     token: Some(Token::new("my token")),
+///     ..Default::default()
+/// };
+/// assert_eq!(format!("{:?}", config.token), "Some(Token(***))");
+/// ```
+#[derive(Clone)]
+pub struct Token(Zeroizing<String>);
+
+impl Token {
+    /// Create a new token.
+    pub fn new(token: impl Into<String>) -> Self {
+        Self(Zeroizing::new(token.into()))
+    }
+
+    /// Read the token from an environment variable.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use ipinfo::Token;
+    ///
+    /// let token = Token::from_env("IPINFO_TOKEN").expect("should be set");
+    /// ```
+    pub fn from_env(key: &str) -> Result<Self, IpError> {
+        let token = env::var(key).map_err(|e| {
+            err!(ConfigError, &format!("token variable {key}: {e}"))
+        })?;
+        Self::parse(token)
+    }
+
+    /// Read the token from a file, ignoring surrounding whitespace.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use ipinfo::Token;
+    ///
+    /// let token = Token::from_file("/run/secrets/ipinfo").expect("should read");
+    /// ```
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, IpError> {
+        let path = path.as_ref();
+        let token = Zeroizing::new(fs::read_to_string(path).map_err(|e| {
+            err!(ConfigError, &format!("token file {}: {e}", path.display()))
+        })?);
+        Self::parse(token.trim().to_string())
+    }
+
+    /// Get the token value.
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+
+    fn parse(token: String) -> Result<Self, IpError> {
+        let token = Self::new(token);
+        if token.0.trim().is_empty() {
+            return Err(err!(ConfigError, "token is empty"));
+        }
+        Ok(token)
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Token(***)")
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl From<String> for Token {
+    fn from(token: String) -> Self {
+        Self::new(token)
+    }
+}
+
+impl From<&str> for Token {
+    fn from(token: &str) -> Self {
+        Self::new(token)
+    }
+}
+
+impl<'de> Deserialize<'de> for Token {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+/// Authenticate requests with an optional token.
+pub(crate) trait TokenAuth {
+    /// Add the bearer token, or leave the request unauthenticated if there is
+    /// none.
+    fn token_auth(self, token: Option<&Token>) -> Self;
+}
+
+impl TokenAuth for RequestBuilder {
+    fn token_auth(self, token: Option<&Token>) -> Self {
+        match token {
+            Some(token) => self.bearer_auth(token.expose_secret()),
+            None => self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IpErrorKind;
+
+    #[test]
+    fn token_is_redacted() {
+        let token = Token::from("secret_token");
+
+        assert_eq!(token.expose_secret(), "secret_token");
+        assert_eq!(format!("{token:?}"), "Token(***)");
+        assert_eq!(token.to_string(), "***");
+    }
+
+    #[test]
+    fn token_from_file() {
+        let path = env::temp_dir().join("ipinfo_token_from_file");
+        fs::write(&path, "secret_token\n").unwrap();
+        let token = Token::from_file(&path).expect("should read");
+        fs::remove_file(&path).unwrap();
+        assert_eq!(token.expose_secret(), "secret_token");
+
+        assert_eq!(
+            Token::from_file(&path).err().unwrap().kind(),
+            IpErrorKind::ConfigError
+        );
+        assert_eq!(
+            Token::from_env("IPINFO_TOKEN_UNSET").err().unwrap().kind(),
+            IpErrorKind::ConfigError
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//   Copyright 2019-2025 IPinfo library developers
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Optional `tracing` instrumentation.
+//!
+//! Spans and events are only emitted with the `tracing` feature enabled.
+//! The access token is never recorded.
+
+/// Emit a tracing event at the given level
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+    };
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::{
+        io::Write,
+        sync::{Arc, Mutex},
+    };
+
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::{BatchReqOpts, IpInfo, IpInfoConfig};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn lookups_are_traced() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/batch"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "8.8.8.8": {
+                        "ip": "8.8.8.8",
+                        "city": "Mountain View",
+                        "region": "California",
+                        "country": "US",
+                        "loc": "37.4056,-122.0775"
+                    }
+                }),
+            ))
+            .mount(&mock_server)
+            .await;
+
+        let mut ipinfo = IpInfo::new(IpInfoConfig {
+            token: Some("secret_token".into()),
+            base_url: Some(mock_server.uri()),
+            ..Default::default()
+        })
+        .expect("should construct");
+
+        ipinfo
+            .lookup_batch(&["8.8.8.8", "10.0.0.1"], BatchReqOpts::default())
+            .await
+            .expect("should lookup");
+        ipinfo.lookup("8.8.8.8").await.expect("should lookup");
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone())
+            .expect("should be utf-8");
+        assert!(output.contains("bogon address"));
+        assert!(output.contains("dispatching batch"));
+        assert!(output.contains("cache hit"));
+        assert!(output.contains("ip=\"8.8.8.8\""));
+        assert!(!output.contains("secret_token"));
+    }
+}
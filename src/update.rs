@@ -0,0 +1,554 @@
+//   Copyright 2019-2025 IPinfo library developers
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Keeping local databases up to date.
+//!
+//! `ReloadingDatabase` watches a database file and swaps in the new data
+//! when the file is replaced. Lookups in progress keep using the data they
+//! started with. `DatabaseDownload` fetches a fresh database, verifies its
+//! SHA-256 checksum and atomically replaces the local file.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # #[cfg(feature = "mmdb")]
+//! # async fn run() -> Result<(), ipinfo::IpError> {
+//! use std::{sync::Arc, time::Duration};
+//! use ipinfo::{DatabaseDownload, IpInfoLiteDb, ReloadingDatabase};
+//!
+//! let download = DatabaseDownload::new("https://ipinfo.io/data/ipinfo_lite.mmdb")
+//!     .checksum_url("https://ipinfo.io/data/ipinfo_lite.mmdb/checksums")
+//!     .token("<token>");
+//! download.download("ipinfo_lite.mmdb").await?;
+//!
+//! let db = Arc::new(ReloadingDatabase::<IpInfoLiteDb>::open("ipinfo_lite.mmdb")?);
+//! let _watcher = db.watch(Duration::from_secs(60));
+//!
+//! // Later, e.g. daily
+//! download.download("ipinfo_lite.mmdb").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    fs, io,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex, RwLock},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    token::TokenAuth, IpDetails, IpError, LocalDatabase, Token, VERSION,
+};
+
+/// A local database that can be loaded from a file.
+pub trait DatabaseFile: Sized {
+    /// Load the database from a file.
+    fn load(path: &Path) -> Result<Self, IpError>;
+}
+
+#[cfg(feature = "mmdb")]
+impl DatabaseFile for crate::IpInfoLiteDb {
+    fn load(path: &Path) -> Result<Self, IpError> {
+        Self::open(path)
+    }
+}
+
+#[cfg(feature = "mmdb")]
+impl DatabaseFile for crate::IpInfoCoreDb {
+    fn load(path: &Path) -> Result<Self, IpError> {
+        Self::open(path)
+    }
+}
+
+#[cfg(feature = "csv")]
+impl DatabaseFile for crate::IpInfoLiteCsv {
+    fn load(path: &Path) -> Result<Self, IpError> {
+        Self::open(path)
+    }
+}
+
+#[cfg(feature = "csv")]
+impl DatabaseFile for crate::IpInfoCoreCsv {
+    fn load(path: &Path) -> Result<Self, IpError> {
+        Self::open(path)
+    }
+}
+
+// Identify a version of a file by its modification time and size
+fn file_stamp(path: &Path) -> Result<(SystemTime, u64), IpError> {
+    let metadata = fs::metadata(path).map_err(|e| {
+        err!(DatabaseError, &format!("{}: {e}", path.display()))
+    })?;
+    let modified = metadata
+        .modified()
+        .map_err(|e| err!(DatabaseError, &e.to_string()))?;
+    Ok((modified, metadata.len()))
+}
+
+/// A database file which is reloaded when it is replaced.
+///
+/// Replace the file atomically, e.g. by renaming a complete download over
+/// it as `DatabaseDownload` does, so a partially written file is never
+/// loaded.
+pub struct ReloadingDatabase<D> {
+    path: PathBuf,
+    current: RwLock<Arc<D>>,
+    stamp: Mutex<(SystemTime, u64)>,
+}
+
+impl<D: DatabaseFile> ReloadingDatabase<D> {
+    /// Load a database file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, IpError> {
+        let path = path.as_ref().to_path_buf();
+        let stamp = file_stamp(&path)?;
+        let db = D::load(&path)?;
+        Ok(Self {
+            path,
+            current: RwLock::new(Arc::new(db)),
+            stamp: Mutex::new(stamp),
+        })
+    }
+
+    /// The currently loaded database.
+    pub fn current(&self) -> Arc<D> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Reload the database if the file has changed, returning whether it
+    /// was reloaded. The current data is kept if loading fails.
+    pub fn reload(&self) -> Result<bool, IpError> {
+        let mut stamp = self.stamp.lock().unwrap();
+        let latest = file_stamp(&self.path)?;
+        if latest == *stamp {
+            return Ok(false);
+        }
+
+        let db = D::load(&self.path)?;
+        *self.current.write().unwrap() = Arc::new(db);
+        *stamp = latest;
+        event!(info, path = %self.path.display(), "reloaded database");
+        Ok(true)
+    }
+
+    /// Check the file for changes every `interval` on a background thread,
+    /// until the returned watcher is dropped.
+    pub fn watch(self: &Arc<Self>, interval: Duration) -> DatabaseWatcher
+    where
+        D: Send + Sync + 'static,
+    {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let db = self.clone();
+        thread::spawn(move || {
+            while let Err(mpsc::RecvTimeoutError::Timeout) =
+                stopped.recv_timeout(interval)
+            {
+                if let Err(_e) = db.reload() {
+                    event!(warn, error = %_e, "failed to reload database");
+                }
+            }
+        });
+        DatabaseWatcher { _stop: stop }
+    }
+}
+
+impl<D: DatabaseFile + LocalDatabase> LocalDatabase for ReloadingDatabase<D> {
+    fn lookup_details(&self, ip: &str) -> Result<Option<IpDetails>, IpError> {
+        self.current().lookup_details(ip)
+    }
+}
+
+/// Stops watching a database file when dropped.
+pub struct DatabaseWatcher {
+    _stop: mpsc::Sender<()>,
+}
+
+/// Checksums of a downloadable database.
+#[derive(Deserialize)]
+struct Checksums {
+    checksums: ChecksumsDetails,
+}
+
+#[derive(Deserialize)]
+struct ChecksumsDetails {
+    sha256: String,
+}
+
+// Compute the hex encoded SHA-256 checksum of a file
+fn file_sha256(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// How long connecting and waiting for more data may take by default
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Downloads database files with checksum verification.
+///
+/// Pass the access token with `token` rather than in the URLs: it is sent
+/// in a header, and request errors leave the URLs out.
+pub struct DatabaseDownload {
+    client: reqwest::Client,
+    url: String,
+    checksum_url: Option<String>,
+    sha256: Option<String>,
+    token: Option<Token>,
+}
+
+impl DatabaseDownload {
+    /// Download the database from the given URL.
+    ///
+    /// The default HTTP client gives up on connections which take longer
+    /// than 30 seconds to establish or stall for 60 seconds. Downloads may
+    /// take long, so there is no limit on the total time.
+    ///
+    /// # Panics
+    ///
+    /// Like `reqwest::Client::new`, if the TLS backend cannot be
+    /// initialized.
+    pub fn new(url: impl Into<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .read_timeout(READ_TIMEOUT)
+            .build()
+            .expect("should build the HTTP client");
+        Self {
+            client,
+            url: url.into(),
+            checksum_url: None,
+            sha256: None,
+            token: None,
+        }
+    }
+
+    /// Verify the download against the SHA-256 checksum published at this
+    /// URL, in the IPinfo `{"checksums": {"sha256": ...}}` format.
+    pub fn checksum_url(mut self, url: impl Into<String>) -> Self {
+        self.checksum_url = Some(url.into());
+        self
+    }
+
+    /// Verify the download against a known hex SHA-256 checksum.
+    pub fn sha256(mut self, checksum: impl Into<String>) -> Self {
+        self.sha256 = Some(checksum.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Authenticate the requests with an access token.
+    pub fn token(mut self, token: impl Into<Token>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Send the requests with the given HTTP client.
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    // Send a request, leaving the URL out of errors as it may hold a token
+    async fn get(&self, url: &str) -> Result<reqwest::Response, IpError> {
+        self.client
+            .get(url)
+            .header(
+                reqwest::header::USER_AGENT,
+                format!("IPinfoClient/Rust/{VERSION}"),
+            )
+            .token_auth(self.token.as_ref())
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| e.without_url().into())
+    }
+
+    // Get the expected checksum of the database
+    async fn expected_sha256(&self) -> Result<Option<String>, IpError> {
+        if let Some(sha256) = &self.sha256 {
+            return Ok(Some(sha256.clone()));
+        }
+        let Some(url) = &self.checksum_url else {
+            return Ok(None);
+        };
+        let resp = self.get(url).await?;
+        let body = resp.text().await.map_err(reqwest::Error::without_url)?;
+        let checksums: Checksums = serde_json::from_str(&body)?;
+        Ok(Some(checksums.checksums.sha256.to_ascii_lowercase()))
+    }
+
+    /// Check whether the published database differs from the file at
+    /// `path`. Always true without a checksum or a local file.
+    pub async fn check_for_update(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<bool, IpError> {
+        let Some(expected) = self.expected_sha256().await? else {
+            return Ok(true);
+        };
+        Ok(match file_sha256(path.as_ref()) {
+            Ok(actual) => actual != expected,
+            Err(_) => true,
+        })
+    }
+
+    /// Download the database to `path` unless the local file is already
+    /// up to date, returning whether it was replaced.
+    ///
+    /// The file is replaced atomically, and only once the download has
+    /// matched the expected checksum.
+    pub async fn download(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<bool, IpError> {
+        let path = path.as_ref();
+        let expected = self.expected_sha256().await?;
+        if let (Some(expected), Ok(actual)) = (&expected, file_sha256(path)) {
+            if actual == *expected {
+                return Ok(false);
+            }
+        }
+
+        // Write next to the target so the rename stays on one filesystem
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".download");
+        let tmp = PathBuf::from(tmp);
+        let resp = self.get(&self.url).await?;
+        if let Err(e) = Self::install(resp, &tmp, path, expected).await {
+            let _ = fs::remove_file(&tmp);
+            return Err(e);
+        }
+        event!(info, path = %path.display(), "downloaded database");
+        Ok(true)
+    }
+
+    // Stream a download into `tmp`, and rename it to `path` if it matches
+    // the expected checksum
+    async fn install(
+        mut resp: reqwest::Response,
+        tmp: &Path,
+        path: &Path,
+        expected: Option<String>,
+    ) -> Result<(), IpError> {
+        let io_error = |e: io::Error| {
+            err!(DatabaseError, &format!("{}: {e}", path.display()))
+        };
+
+        let mut file = fs::File::create(tmp).map_err(io_error)?;
+        let mut hasher = Sha256::new();
+        while let Some(chunk) =
+            resp.chunk().await.map_err(reqwest::Error::without_url)?
+        {
+            hasher.update(&chunk);
+            file.write_all(&chunk).map_err(io_error)?;
+        }
+        file.sync_all().map_err(io_error)?;
+
+        if let Some(expected) = expected {
+            let actual = format!("{:x}", hasher.finalize());
+            if actual != expected {
+                return Err(err!(
+                    DatabaseError,
+                    &format!(
+                        "checksum mismatch: expected {expected}, got {actual}"
+                    )
+                ));
+            }
+        }
+        fs::rename(tmp, path).map_err(io_error)
+    }
+}
+
+#[cfg(all(test, feature = "csv"))]
+mod tests {
+    use super::*;
+    use crate::{IpErrorKind, IpInfoLiteCsv};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const HEADER: &str = "network,country_code,asn\n";
+
+    fn sha256(data: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(data))
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir()
+            .join(format!("ipinfo_{name}_{}.csv", std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    // Replace a file atomically, the way database updates are installed
+    fn replace(path: &Path, contents: &str) {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, contents).unwrap();
+        fs::rename(&tmp, path).unwrap();
+    }
+
+    #[test]
+    fn reload_database() {
+        let path = temp_path("reload");
+        fs::write(&path, format!("{HEADER}8.8.8.0/24,US,AS15169\n")).unwrap();
+
+        let db = Arc::new(
+            ReloadingDatabase::<IpInfoLiteCsv>::open(&path)
+                .expect("should open"),
+        );
+        let before = db.current();
+        assert!(!db.reload().expect("should check"));
+
+        replace(&path, &format!("{HEADER}8.8.8.0/24,DE,AS3320\n"));
+        let watcher = db.watch(Duration::from_millis(10));
+        let mut reloaded = false;
+        for _ in 0..200 {
+            if db.current().lookup("8.8.8.8").unwrap().country_code == "DE" {
+                reloaded = true;
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(reloaded);
+        drop(watcher);
+
+        // Lookups holding the previous data are unaffected
+        assert_eq!(before.lookup("8.8.8.8").unwrap().country_code, "US");
+
+        // Broken files keep the current data
+        replace(&path, "no ranges here\n1,2\n");
+        assert!(db.reload().is_err());
+        assert_eq!(db.current().lookup("8.8.8.8").unwrap().asn, "AS3320");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn download_database() {
+        let mock_server = MockServer::start().await;
+        let data = format!("{HEADER}8.8.8.0/24,US,AS15169\n");
+
+        Mock::given(method("GET"))
+            .and(path("/ipinfo_lite.csv"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(&data))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/ipinfo_lite.csv/checksums"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({
+                    "checksums": {"sha256": sha256(data.as_bytes())}
+                }),
+            ))
+            .mount(&mock_server)
+            .await;
+
+        let path = temp_path("download");
+        let download = DatabaseDownload::new(format!(
+            "{}/ipinfo_lite.csv",
+            mock_server.uri()
+        ))
+        .checksum_url(format!(
+            "{}/ipinfo_lite.csv/checksums",
+            mock_server.uri()
+        ));
+
+        assert!(download.check_for_update(&path).await.unwrap());
+        assert!(download.download(&path).await.expect("should download"));
+        assert!(!download.check_for_update(&path).await.unwrap());
+        assert!(!download.download(&path).await.expect("should check"));
+        let db = IpInfoLiteCsv::open(&path).expect("should open");
+        assert_eq!(db.lookup("8.8.8.8").unwrap().asn, "AS15169");
+
+        // A mismatching download does not replace the file
+        let download = DatabaseDownload::new(format!(
+            "{}/ipinfo_lite.csv",
+            mock_server.uri()
+        ))
+        .sha256("0".repeat(64));
+        assert_eq!(
+            download.download(&path).await.err().unwrap().kind(),
+            IpErrorKind::DatabaseError
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), data);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
+
+#[cfg(all(test, feature = "mmdb"))]
+mod mmdb_tests {
+    use super::*;
+    use crate::{mmdb::writer::MmdbWriter, IpInfoLiteDb};
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn download_mmdb_database() {
+        let mock_server = MockServer::start().await;
+        let mut writer = MmdbWriter::default();
+        writer.insert(
+            "8.8.8.0/24",
+            serde_json::json!({
+                "country_code": "US",
+                "country": "United States",
+                "asn": "AS15169"
+            }),
+        );
+        let data = writer.build();
+
+        Mock::given(method("GET"))
+            .and(path("/ipinfo_lite.mmdb"))
+            .and(header("authorization", "Bearer test_token"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(data))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/ipinfo_lite.mmdb"))
+            .and(query_param("token", "secret_token"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock_server)
+            .await;
+
+        let path = std::env::temp_dir()
+            .join(format!("ipinfo_download_{}.mmdb", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let url = format!("{}/ipinfo_lite.mmdb", mock_server.uri());
+
+        // The token is sent in a header
+        let download = DatabaseDownload::new(&url).token("test_token");
+        assert!(download.download(&path).await.expect("should download"));
+        let db = ReloadingDatabase::<IpInfoLiteDb>::open(&path)
+            .expect("should open");
+        let details = db.current().lookup("8.8.8.8").expect("should lookup");
+        assert_eq!(details.asn, "AS15169");
+
+        // Errors leave out URLs, which may hold a token
+        let download =
+            DatabaseDownload::new(format!("{url}?token=secret_token"));
+        let err = download.download(&path).await.err().unwrap();
+        assert_eq!(err.status(), Some(500));
+        assert!(!err.to_string().contains("secret_token"));
+        assert!(fs::metadata(&path).is_ok());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
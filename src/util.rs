@@ -13,12 +13,53 @@
 //   limitations under the License.
 
 //! IPInfo Utility Functions
-use std::time::Duration;
+use std::{num::NonZeroUsize, time::Duration};
+
+use reqwest::Url;
+
+use crate::IpError;
 
 pub const BATCH_MAX_SIZE: u64 = 1000;
 pub const BATCH_REQ_TIMEOUT_DEFAULT: Duration = Duration::from_secs(5);
+pub const UPLOAD_MAX_SIZE: usize = 500_000;
 
 const CACHE_KEY_VERSION: &str = "1";
 pub fn cache_key(k: &str) -> String {
     format!("{k}:{CACHE_KEY_VERSION}")
 }
+
+/// The IP address of a cache key of the current version.
+pub(crate) fn cache_key_ip(key: &str) -> Option<&str> {
+    key.strip_suffix(CACHE_KEY_VERSION)?.strip_suffix(':')
+}
+
+/// Validate the settings shared by all clients and return the cache capacity.
+pub(crate) fn validate_config(
+    cache_size: usize,
+    timeout: Duration,
+    base_urls: [Option<&str>; 2],
+) -> Result<NonZeroUsize, IpError> {
+    if timeout.is_zero() {
+        return Err(err!(ConfigError, "timeout must be greater than zero"));
+    }
+    for url in base_urls.into_iter().flatten() {
+        match Url::parse(url) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {}
+            Ok(_) => {
+                return Err(err!(
+                    ConfigError,
+                    &format!("base URL {url} must use http or https")
+                ))
+            }
+            Err(e) => {
+                return Err(err!(
+                    ConfigError,
+                    &format!("invalid base URL {url}: {e}")
+                ))
+            }
+        }
+    }
+    NonZeroUsize::new(cache_size).ok_or_else(|| {
+        err!(ConfigError, "cache size must be greater than zero")
+    })
+}